@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+
+/// Index into [`Program::registers`].
+pub type Reg = usize;
+
+/// The register the CRT draws from, always present and starting at 1. Every other register
+/// starts at 0.
+pub const X: Reg = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Value(isize),
+    Reg(Reg),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Noop,
+    Add(Reg, Operand),
+    Sub(Reg, Operand),
+    Mul(Reg, Operand),
+    /// Continues at the given instruction.
+    Jmp(usize),
+    Jnz(Operand, usize),
+}
+
+impl Op {
+    /// How many cycles the instruction takes. Its effect only lands once they're all over.
+    pub fn cycles(&self) -> usize {
+        match self {
+            Op::Noop => 1,
+            Op::Add(..) | Op::Sub(..) => 2,
+            Op::Mul(..) => 4,
+            Op::Jmp(_) | Op::Jnz(..) => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub ops: Vec<Op>,
+    /// Register names, `x` first.
+    pub registers: Vec<String>,
+    /// The source line of each op.
+    pub source: Vec<String>,
+}
+
+/// Assembles a program. On top of `noop` and `addx V`, it understands
+///
+/// - `addx R V`, `subx R V` and `mulx R V`, storing into register `R` (`x` when left out)
+/// - `jmp T` and `jnz V T`, where `T` is a label or an offset from the current instruction
+/// - `name:` on a line of its own, labelling the next instruction
+///
+/// `V` is either a number or a register. Registers spring into existence when first named.
+pub fn assemble(source: &str) -> Result<Program> {
+    let mut labels = HashMap::new();
+    let mut lines = vec![];
+
+    for (idx, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.strip_suffix(':') {
+            Some(label) => {
+                if !is_name(label) {
+                    return Err(anyhow!("line {}: {line}: bad label name", idx + 1));
+                }
+                if labels.insert(label, lines.len()).is_some() {
+                    return Err(anyhow!("line {}: {line}: label defined twice", idx + 1));
+                }
+            }
+            None => lines.push((idx + 1, line)),
+        }
+    }
+
+    let mut assembler = Assembler {
+        labels,
+        len: lines.len(),
+        registers: vec!["x".into()],
+    };
+    let ops = lines
+        .iter()
+        .enumerate()
+        .map(|(pos, (number, line))| {
+            assembler
+                .op(pos, line)
+                .with_context(|| format!("line {number}: {line}"))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Program {
+        ops,
+        registers: assembler.registers,
+        source: lines.iter().map(|(_, line)| line.to_string()).collect(),
+    })
+}
+
+fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct Assembler<'a> {
+    labels: HashMap<&'a str, usize>,
+    len: usize,
+    registers: Vec<String>,
+}
+
+impl Assembler<'_> {
+    fn op(&mut self, pos: usize, line: &str) -> Result<Op> {
+        let mut words = line.split_whitespace();
+        let name = words.next().context("empty instruction")?;
+        let args: Vec<&str> = words.collect();
+
+        Ok(match (name, args.as_slice()) {
+            ("noop", []) => Op::Noop,
+            ("addx", [value]) => Op::Add(X, self.operand(value)?),
+            ("subx", [value]) => Op::Sub(X, self.operand(value)?),
+            ("mulx", [value]) => Op::Mul(X, self.operand(value)?),
+            ("addx", [reg, value]) => Op::Add(self.register(reg)?, self.operand(value)?),
+            ("subx", [reg, value]) => Op::Sub(self.register(reg)?, self.operand(value)?),
+            ("mulx", [reg, value]) => Op::Mul(self.register(reg)?, self.operand(value)?),
+            ("jmp", [target]) => Op::Jmp(self.target(pos, target)?),
+            ("jnz", [value, target]) => Op::Jnz(self.operand(value)?, self.target(pos, target)?),
+            ("noop" | "addx" | "subx" | "mulx" | "jmp" | "jnz", _) => {
+                return Err(anyhow!("wrong number of arguments for {name}"))
+            }
+            _ => return Err(anyhow!("unknown op {name}")),
+        })
+    }
+
+    fn register(&mut self, name: &str) -> Result<Reg> {
+        if !is_name(name) {
+            return Err(anyhow!("bad register name {name}"));
+        }
+        if let Some(reg) = self.registers.iter().position(|known| known == name) {
+            return Ok(reg);
+        }
+        self.registers.push(name.into());
+        Ok(self.registers.len() - 1)
+    }
+
+    fn operand(&mut self, s: &str) -> Result<Operand> {
+        match s.parse() {
+            Ok(value) => Ok(Operand::Value(value)),
+            Err(_) => Ok(Operand::Reg(self.register(s)?)),
+        }
+    }
+
+    /// Jumping just past the last instruction is allowed and ends the program.
+    fn target(&self, pos: usize, s: &str) -> Result<usize> {
+        let target = match s.parse::<isize>() {
+            Ok(offset) => pos.checked_add_signed(offset),
+            Err(_) => Some(*self.labels.get(s).context(format!("unknown label {s}"))?),
+        };
+        target
+            .filter(|target| *target <= self.len)
+            .context(format!("jump to {s} leaves the program"))
+    }
+}
+
+/// One cycle of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// Counting from 1.
+    pub number: usize,
+    /// Index of the instruction being run.
+    pub pc: usize,
+    pub op: Op,
+    /// `x` while the cycle is under way, which is what the puzzle samples and the CRT draws.
+    pub during: isize,
+    /// `x` once the cycle is over, including the effect of an instruction finishing on it.
+    pub after: isize,
+}
+
+/// Runs a program one cycle at a time, yielding the `x` register during each cycle with the
+/// cycle's number, starting at 1. Arithmetic wraps around.
+pub struct Crt {
+    clock: usize,
+    registers: Vec<isize>,
+    program: Program,
+    cursor: usize,
+    busy_time: usize,
+}
+
+impl Crt {
+    pub fn new(s: &str) -> Result<Self> {
+        Ok(Self::from(assemble(s)?))
+    }
+
+    /// Source line of the instruction at `pc`.
+    pub fn line(&self, pc: usize) -> &str {
+        &self.program.source[pc]
+    }
+
+    /// Runs a single cycle. An instruction takes effect at the end of its last cycle.
+    pub fn step(&mut self) -> Option<Cycle> {
+        let pc = self.cursor;
+        let op = *self.program.ops.get(pc)?;
+        if self.busy_time == 0 {
+            self.busy_time = op.cycles();
+        }
+
+        let during = self.registers[X];
+        self.busy_time -= 1;
+        self.clock += 1;
+        if self.busy_time == 0 {
+            self.execute(op);
+        }
+
+        Some(Cycle {
+            number: self.clock,
+            pc,
+            op,
+            during,
+            after: self.registers[X],
+        })
+    }
+
+    /// Every register with its current value, `x` first.
+    pub fn registers(&self) -> impl Iterator<Item = (&str, isize)> {
+        let names = self.program.registers.iter().map(String::as_str);
+        names.zip(self.registers.iter().copied())
+    }
+
+    fn value(&self, operand: Operand) -> isize {
+        match operand {
+            Operand::Value(value) => value,
+            Operand::Reg(reg) => self.registers[reg],
+        }
+    }
+
+    fn execute(&mut self, op: Op) {
+        let (reg, value, f): (_, _, fn(isize, isize) -> isize) = match op {
+            Op::Noop => {
+                self.cursor += 1;
+                return;
+            }
+            Op::Add(reg, operand) => (reg, self.value(operand), isize::wrapping_add),
+            Op::Sub(reg, operand) => (reg, self.value(operand), isize::wrapping_sub),
+            Op::Mul(reg, operand) => (reg, self.value(operand), isize::wrapping_mul),
+            Op::Jmp(target) => {
+                self.cursor = target;
+                return;
+            }
+            Op::Jnz(operand, target) => {
+                match self.value(operand) {
+                    0 => self.cursor += 1,
+                    _ => self.cursor = target,
+                }
+                return;
+            }
+        };
+        self.registers[reg] = f(self.registers[reg], value);
+        self.cursor += 1;
+    }
+}
+
+impl From<Program> for Crt {
+    fn from(program: Program) -> Self {
+        let mut registers = vec![0; program.registers.len()];
+        registers[X] = 1;
+        Self {
+            clock: 0,
+            registers,
+            program,
+            cursor: 0,
+            busy_time: 0,
+        }
+    }
+}
+
+impl Iterator for Crt {
+    type Item = (isize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().map(|cycle| (cycle.during, cycle.number))
+    }
+}
+
+#[test]
+fn assembles_classic_programs() -> Result<()> {
+    let program = assemble("noop\naddx 3\naddx -5")?;
+    assert_eq!(
+        program.ops,
+        vec![
+            Op::Noop,
+            Op::Add(X, Operand::Value(3)),
+            Op::Add(X, Operand::Value(-5))
+        ]
+    );
+    assert_eq!(program.registers, vec!["x"]);
+
+    let values: Vec<_> = Crt::from(program).collect();
+    assert_eq!(values, vec![(1, 1), (1, 2), (1, 3), (4, 4), (4, 5)]);
+    Ok(())
+}
+
+#[test]
+fn runs_loops_over_registers() -> Result<()> {
+    // x = 1 + 3 * 4 by repeated addition, then doubled and one taken off
+    let source = "
+addx n 4
+top:
+addx 3
+subx n 1
+jnz n top
+mulx 2
+subx 1
+";
+    let program = assemble(source)?;
+    assert_eq!(program.registers, vec!["x", "n"]);
+    assert_eq!(program.ops[3], Op::Jnz(Operand::Reg(1), 1));
+
+    let mut crt = Crt::from(program);
+    let cycles = crt.by_ref().count();
+    assert_eq!(
+        crt.registers().collect::<Vec<_>>(),
+        vec![("x", 25), ("n", 0)]
+    );
+    assert_eq!(cycles, 2 + 4 * (2 + 2 + 2) + 4 + 2);
+    Ok(())
+}
+
+#[test]
+fn jumps_by_offset() -> Result<()> {
+    let program = assemble("jmp 2\naddx 100\naddx y -1\njnz 0 -3")?;
+    assert_eq!(
+        program.ops,
+        vec![
+            Op::Jmp(2),
+            Op::Add(X, Operand::Value(100)),
+            Op::Add(1, Operand::Value(-1)),
+            Op::Jnz(Operand::Value(0), 0)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn assembler_errors_have_line_numbers() {
+    let error = |source: &str| format!("{:#}", assemble(source).unwrap_err());
+    assert_eq!(error("noop\n\nfoo 1"), "line 3: foo 1: unknown op foo");
+    assert_eq!(
+        error("noop\naddx 1 2 3"),
+        "line 2: addx 1 2 3: wrong number of arguments for addx"
+    );
+    assert_eq!(
+        error("jmp nowhere"),
+        "line 1: jmp nowhere: unknown label nowhere"
+    );
+    assert_eq!(
+        error("jmp -1"),
+        "line 1: jmp -1: jump to -1 leaves the program"
+    );
+    assert_eq!(error("a:\nnoop\na:"), "line 3: a:: label defined twice");
+    assert_eq!(error("addx X 1"), "line 1: addx X 1: bad register name X");
+}
@@ -1,127 +1,137 @@
-use std::{collections::HashSet, str::FromStr};
+use std::collections::HashSet;
 
-use anyhow::{anyhow, Context, Error, Result};
+use anyhow::{anyhow, Context, Result};
+
+mod cpu;
+mod screen;
+mod trace;
+use cpu::Crt;
+use screen::Screen;
+use trace::{Breakpoint, Tracer};
 
 fn main() -> Result<()> {
     let input = include_str!("../input.txt");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("run") = args.first().map(String::as_str) {
+        let path = args.get(1).context("run needs a program")?;
+        return run(&std::fs::read_to_string(path)?, &args[2..]);
+    }
+    if let Some("trace") = args.first().map(String::as_str) {
+        return trace(input, &args[1..]);
+    }
+
     let part1: isize = part1(input)?;
     println!("part1: {part1}");
 
-    part2(input)?;
+    let screen = part2(input)?;
+    print!("{screen}");
+    println!("part2: {}", screen.read()?);
 
     Ok(())
 }
 
-fn parse(s: &str) -> Result<Vec<Op>> {
-    s.lines().map(|line| line.parse()).collect()
-}
-
-fn part1(input: &str) -> Result<isize> {
-    let crt = Crt::new(input)?;
-    let times = HashSet::from([20, 60, 100, 140, 180, 220]);
-    Ok(crt
-        .into_iter()
-        .filter_map(|(val, clock)| times.contains(&clock).then_some(val * clock as isize))
-        .sum())
-}
-
-fn part2(input: &str) -> Result<()> {
-    let mut crt = Crt::new(input)?;
-
-    for _ in 0..6isize {
-        let mut old_reg = 1;
-
-        for x in 0..40isize {
-            let coord = x;
-            let sprite = [coord - 1, coord, coord + 1];
-            if sprite.contains(&old_reg) {
-                print!("#");
-            } else {
-                print!(".");
-            }
-
-            let (reg, _) = crt.next().context("couldn't get next Crt")?;
-            old_reg = reg
-        }
-        println!()
+/// Cycles `run` gives a program to stop in unless told otherwise with `--max-cycles N`.
+const MAX_CYCLES: usize = 10_000_000;
+
+/// Runs any program until it stops, then prints its registers.
+fn run(source: &str, args: &[String]) -> Result<()> {
+    let max_cycles = match args {
+        [] => MAX_CYCLES,
+        [flag, value] if flag == "--max-cycles" => value.parse()?,
+        _ => return Err(anyhow!("expected --max-cycles N, got {}", args.join(" "))),
+    };
+
+    let mut crt = Crt::new(source)?;
+    let cycles = run_until_stopped(&mut crt, max_cycles)?;
+    println!("cycles: {cycles}");
+    for (name, value) in crt.registers() {
+        println!("{name}: {value}");
     }
     Ok(())
 }
 
-enum Op {
-    Noop,
-    Add(isize),
+/// Number of cycles the program ran for, or an error if it's still going after
+/// `max_cycles`.
+fn run_until_stopped(crt: &mut Crt, max_cycles: usize) -> Result<usize> {
+    let cycles = crt.by_ref().take(max_cycles).count();
+    match crt.step() {
+        Some(_) => Err(anyhow!("still running after {max_cycles} cycles")),
+        None => Ok(cycles),
+    }
 }
 
-impl FromStr for Op {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "noop" {
-            return Ok(Op::Noop);
+/// Prints a trace of every cycle until one of the `--break N` or `--break NAME=VALUE`
+/// breakpoints is hit. `--program PATH` traces another program than the puzzle input.
+fn trace(input: &str, args: &[String]) -> Result<()> {
+    let mut source = input.to_string();
+    let mut breakpoints = vec![];
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().context(format!("{flag} needs a value"))?;
+        match flag.as_str() {
+            "--break" => breakpoints.push(value.parse::<Breakpoint>()?),
+            "--program" => source = std::fs::read_to_string(value)?,
+            _ => return Err(anyhow!("unknown flag {flag}")),
         }
+    }
 
-        let (op, num) = s.split_once(' ').context("couldn't split addx")?;
-        if op != "addx" {
-            return Err(anyhow!("unknown op {}", op));
+    let mut tracer = Tracer::new(Crt::new(&source)?, breakpoints)?;
+    let (cycles, hit) = tracer.resume();
+    print!("{}", tracer.table(&cycles));
+    if let Some(breakpoint) = hit {
+        println!("stopped at {breakpoint:?}");
+        for (name, value) in tracer.crt.registers() {
+            println!("{name}: {value}");
         }
-        Ok(Op::Add(num.parse::<isize>()?))
     }
+    Ok(())
 }
 
-#[derive(Default)]
-struct Crt {
-    clock: usize,
-    register: isize,
-    ops: Vec<Op>,
-    cursor: usize,
-    busy_time: usize,
+fn part1(input: &str) -> Result<isize> {
+    let crt = Crt::new(input)?;
+    let times = HashSet::from([20, 60, 100, 140, 180, 220]);
+    let last = *times.iter().max().unwrap_or(&0);
+    Ok(crt
+        .take_while(|(_, clock)| *clock <= last)
+        .filter_map(|(val, clock)| times.contains(&clock).then_some(val * clock as isize))
+        .sum())
 }
 
-impl Crt {
-    fn new(s: &str) -> Result<Self> {
-        Ok(Crt {
-            ops: parse(s)?,
-            register: 1,
-            busy_time: 1,
-            ..Default::default()
-        })
-    }
-}
-
-impl Iterator for Crt {
-    type Item = (isize, usize);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.busy_time == 0 {
-            match self.ops.get(self.cursor)? {
-                Op::Noop => {
-                    self.busy_time = 1;
-                }
-                Op::Add(val) => {
-                    self.busy_time = 2;
-                    self.register += val;
-                }
-            };
-            self.cursor += 1;
-        }
-
-        self.busy_time = self.busy_time.saturating_sub(1);
-        self.clock += 1;
-
-        Some((self.register, self.clock))
-    }
+fn part2(input: &str) -> Result<Screen> {
+    Screen::draw(Crt::new(input)?.map(|(val, _)| val))
 }
 
 #[test]
 fn part1_works() {
     let input = include_str!("../test/fixtures/long_example.txt");
     let part1 = part1(input).unwrap();
-    assert_eq!(part1, 13140); // test weirdly fails even though the answer is right
+    assert_eq!(part1, 13140);
 }
 
 #[test]
-fn part2_works() {
+fn part2_works() -> Result<()> {
     let input = include_str!("../test/fixtures/long_example.txt");
-    part2(input).unwrap();
+    assert_eq!(
+        part2(input)?.to_string(),
+        "##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+"
+    );
+    Ok(())
+}
+
+#[test]
+fn run_gives_up_on_endless_programs() -> Result<()> {
+    let mut crt = Crt::new("l:\njmp l")?;
+    let err = run_until_stopped(&mut crt, 1000).unwrap_err();
+    assert_eq!(err.to_string(), "still running after 1000 cycles");
+
+    let mut crt = Crt::new("noop\naddx 3")?;
+    assert_eq!(run_until_stopped(&mut crt, 3)?, 3);
+    Ok(())
 }
@@ -0,0 +1,155 @@
+use std::fmt::{self, Display};
+
+use anyhow::{anyhow, Result};
+
+pub const WIDTH: usize = 40;
+pub const HEIGHT: usize = 6;
+
+/// Whether the 3 wide sprite centred on `sprite` covers column `x`.
+pub fn covers(sprite: isize, x: usize) -> bool {
+    sprite.abs_diff(x as isize) <= 1
+}
+
+/// Column and row the CRT draws during a cycle, counting cycles from 1.
+pub fn position(cycle: usize) -> (usize, usize) {
+    let idx = cycle - 1;
+    (idx % WIDTH, idx / WIDTH % HEIGHT)
+}
+
+/// What the CRT has drawn, one row per scan line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    pub pixels: [[bool; WIDTH]; HEIGHT],
+}
+
+impl Screen {
+    /// Runs the CRT for a full frame, lighting each pixel the 3 wide sprite covers while it's
+    /// being drawn. `cycles` yields the register during each cycle.
+    pub fn draw(cycles: impl IntoIterator<Item = isize>) -> Result<Self> {
+        let mut pixels = [[false; WIDTH]; HEIGHT];
+        let mut cycles = cycles.into_iter();
+
+        for (y, row) in pixels.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let sprite = cycles
+                    .next()
+                    .ok_or_else(|| anyhow!("program ended before pixel {x},{y}"))?;
+                *pixel = covers(sprite, x);
+            }
+        }
+
+        Ok(Self { pixels })
+    }
+
+    /// Reads the capital letters the screen spells out.
+    pub fn read(&self) -> Result<String> {
+        let mut letters = String::new();
+        let mut unknown = vec![];
+
+        for pos in 0..WIDTH / GLYPH_STRIDE {
+            let x = pos * GLYPH_STRIDE;
+            let glyph: Vec<&[bool]> = self.pixels.iter().map(|row| &row[x..x + 4]).collect();
+            let letter = FONT.iter().find(|(_, rows)| {
+                rows.iter().zip(&glyph).all(|(font, pixels)| {
+                    font.bytes().map(|c| c == b'#').eq(pixels.iter().copied())
+                })
+            });
+
+            match letter {
+                Some((letter, _)) => letters.push(*letter),
+                None => {
+                    letters.push('?');
+                    unknown.push((pos + 1).to_string());
+                }
+            }
+        }
+
+        if !unknown.is_empty() {
+            return Err(anyhow!(
+                "can't read letters {} of {letters}",
+                unknown.join(", ")
+            ));
+        }
+        Ok(letters)
+    }
+}
+
+impl Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.pixels {
+            let line: String = row.iter().map(|lit| if *lit { '#' } else { '.' }).collect();
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Letters are 4 pixels wide with a blank column after each.
+const GLYPH_STRIDE: usize = 5;
+
+const FONT: [(char, [&str; HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[cfg(test)]
+fn screen(rows: [&str; HEIGHT]) -> Screen {
+    Screen {
+        pixels: rows.map(|row| {
+            let mut pixels = [false; WIDTH];
+            for (pixel, c) in pixels.iter_mut().zip(row.chars()) {
+                *pixel = c == '#';
+            }
+            pixels
+        }),
+    }
+}
+
+#[test]
+fn reads_letters() -> Result<()> {
+    let screen = screen([
+        "####..##..###...##....##.####...##.####.",
+        "...#.#..#.#..#.#..#....#.#.......#....#.",
+        "..#..#....###..#..#....#.###.....#...#..",
+        ".#...#....#..#.####....#.#.......#..#...",
+        "#....#..#.#..#.#..#.#..#.#....#..#.#....",
+        "####..##..###..#..#..##..#.....##..####.",
+    ]);
+    assert_eq!(screen.read()?, "ZCBAJFJZ");
+    Ok(())
+}
+
+#[test]
+fn lists_unreadable_letters() {
+    let screen = screen([
+        "####.#..#.......##....##.####...##.####.",
+        "...#.#..#......#..#....#.#.......#....#.",
+        "..#..####......#..#....#.###.....#...#..",
+        ".#...#..#......####....#.#.......#..#...",
+        "#....#..#......#..#.#..#.#....#..#.#....",
+        "####.#..#.#....#..#..##..#.....##..##.#.",
+    ]);
+    let err = screen.read().unwrap_err();
+    assert_eq!(err.to_string(), "can't read letters 3, 8 of ZH?AJFJ?");
+}
+
+#[test]
+fn draw_needs_a_full_frame() {
+    assert!(Screen::draw([1; WIDTH * HEIGHT - 1]).is_err());
+    assert!(Screen::draw([1; WIDTH * HEIGHT]).is_ok());
+}
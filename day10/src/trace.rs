@@ -0,0 +1,149 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error, Result};
+
+use crate::{
+    cpu::{Crt, Cycle},
+    screen::{covers, position},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Stops once the cycle with this number is over.
+    Cycle(usize),
+    /// Stops after any cycle that leaves the register holding the value.
+    Register(String, isize),
+}
+
+impl FromStr for Breakpoint {
+    type Err = Error;
+
+    /// `N` for a cycle number, `NAME=VALUE` for a register.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, value)) => Ok(Breakpoint::Register(
+                name.into(),
+                value
+                    .parse()
+                    .context(format!("can't parse register value {value}"))?,
+            )),
+            None => Ok(Breakpoint::Cycle(
+                s.parse().context(format!("can't parse cycle number {s}"))?,
+            )),
+        }
+    }
+}
+
+/// Steps through a program cycle by cycle, stopping at breakpoints.
+pub struct Tracer {
+    pub crt: Crt,
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+impl Tracer {
+    /// Fails if a breakpoint names a register the program doesn't have, as it could never
+    /// be hit.
+    pub fn new(crt: Crt, breakpoints: Vec<Breakpoint>) -> Result<Self> {
+        for breakpoint in &breakpoints {
+            if let Breakpoint::Register(name, _) = breakpoint {
+                if !crt.registers().any(|(reg, _)| reg == name) {
+                    return Err(anyhow!("the program has no register {name}"));
+                }
+            }
+        }
+        Ok(Self { crt, breakpoints })
+    }
+
+    /// Runs until a breakpoint is hit or the program ends, returning the cycles run and the
+    /// breakpoint that stopped them.
+    pub fn resume(&mut self) -> (Vec<Cycle>, Option<Breakpoint>) {
+        let mut cycles = vec![];
+        while let Some(cycle) = self.crt.step() {
+            cycles.push(cycle);
+            let crt = &self.crt;
+            let hit = self.breakpoints.iter().find(|breakpoint| match breakpoint {
+                Breakpoint::Cycle(number) => *number == cycle.number,
+                Breakpoint::Register(name, value) => crt
+                    .registers()
+                    .any(|(reg, current)| reg == name && current == *value),
+            });
+            if hit.is_some() {
+                return (cycles, hit.cloned());
+            }
+        }
+        (cycles, None)
+    }
+
+    /// Execution trace with the pixel the CRT draws during each cycle.
+    pub fn table(&self, cycles: &[Cycle]) -> String {
+        let mut table = format!(
+            "{:>6} {:>4}  {:<16} {:>6} {:>6}  pixel\n",
+            "cycle", "pc", "instruction", "during", "after"
+        );
+        for cycle in cycles {
+            let (x, y) = position(cycle.number);
+            let lit = if covers(cycle.during, x) { '#' } else { '.' };
+            table += &format!(
+                "{:>6} {:>4}  {:<16} {:>6} {:>6}  {x:>2},{y} {lit}\n",
+                cycle.number,
+                cycle.pc,
+                self.crt.line(cycle.pc),
+                cycle.during,
+                cycle.after,
+            );
+        }
+        table
+    }
+}
+
+#[test]
+fn during_and_after_differ_when_addx_lands() -> Result<()> {
+    let mut tracer = Tracer::new(Crt::new("noop\naddx 3\naddx -5")?, vec![])?;
+    let (cycles, hit) = tracer.resume();
+    assert_eq!(hit, None);
+    let values: Vec<_> = cycles.iter().map(|c| (c.during, c.after)).collect();
+    assert_eq!(values, vec![(1, 1), (1, 1), (1, 4), (4, 4), (4, -1)]);
+    Ok(())
+}
+
+#[test]
+fn stops_at_breakpoints() -> Result<()> {
+    let input = include_str!("../test/fixtures/long_example.txt");
+    let breakpoints = vec!["20".parse()?, "x=25".parse()?];
+    let mut tracer = Tracer::new(Crt::new(input)?, breakpoints)?;
+
+    let (cycles, hit) = tracer.resume();
+    assert_eq!(hit, Some(Breakpoint::Cycle(20)));
+    let last = cycles.last().context("ran no cycles")?;
+    // the puzzle's signal strength of 420 at cycle 20
+    assert_eq!(last.during * last.number as isize, 420);
+
+    let (cycles, hit) = tracer.resume();
+    assert_eq!(hit, Some(Breakpoint::Register("x".into(), 25)));
+    let numbers: Vec<usize> = cycles.iter().map(|c| c.number).collect();
+    assert_eq!(numbers, vec![21, 22, 23]);
+
+    assert!("x=y".parse::<Breakpoint>().is_err());
+    let unknown = Tracer::new(Crt::new(input)?, vec!["y=3".parse()?]);
+    assert_eq!(
+        unknown.err().map(|err| err.to_string()),
+        Some("the program has no register y".into())
+    );
+    Ok(())
+}
+
+#[test]
+fn table_shows_pixels() -> Result<()> {
+    let mut tracer = Tracer::new(Crt::new("addx 15\naddx -11")?, vec![])?;
+    let (cycles, _) = tracer.resume();
+    assert_eq!(
+        tracer.table(&cycles),
+        " cycle   pc  instruction      during  after  pixel
+     1    0  addx 15               1      1   0,0 #
+     2    0  addx 15               1     16   1,0 #
+     3    1  addx -11             16     16   2,0 .
+     4    1  addx -11             16      5   3,0 .
+"
+    );
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{worry::Worry, Monkey};
+
+/// Monkeys inspecting one item, round by round, until its rounds start repeating.
+struct Trajectory {
+    /// Monkeys that inspected the item in each round, in order.
+    inspections: Vec<Vec<usize>>,
+    /// Round the repetition goes back to, if the item got that far.
+    cycle_start: Option<usize>,
+}
+
+impl Trajectory {
+    /// Follows one item for up to `rounds` rounds. Worry is kept modulo `modulus`, so there
+    /// are finitely many states at the start of a round and one of them has to come back.
+    fn follow(
+        monkeys: &[Monkey],
+        mut monkey: usize,
+        item: usize,
+        modulus: usize,
+        rounds: u64,
+    ) -> Result<Self> {
+        let mut worry = item % modulus;
+        let mut seen = HashMap::new();
+        let mut inspections = vec![];
+
+        while (inspections.len() as u64) < rounds {
+            if let Some(start) = seen.insert((monkey, worry), inspections.len()) {
+                return Ok(Self {
+                    inspections,
+                    cycle_start: Some(start),
+                });
+            }
+
+            // an item thrown to a later monkey gets inspected again in the same round
+            let mut round = vec![];
+            loop {
+                round.push(monkey);
+                let current = &monkeys[monkey];
+                let new = current
+                    .op
+                    .eval(&(worry as u128))
+                    .with_context(|| format!("{} doesn't fit for old = {worry}", current.op))?;
+                worry = new.rem_usize(modulus);
+                let next = current.throw.target(&worry);
+                if next <= monkey {
+                    monkey = next;
+                    break;
+                }
+                monkey = next;
+            }
+            inspections.push(round);
+        }
+
+        Ok(Self {
+            inspections,
+            cycle_start: None,
+        })
+    }
+
+    /// Adds the item's inspections over `rounds` rounds to `counts`.
+    fn count(&self, rounds: u64, counts: &mut [u64]) {
+        let mut add = |range: std::ops::Range<usize>, times: u64| {
+            for monkey in self.inspections[range].iter().flatten() {
+                counts[*monkey] += times;
+            }
+        };
+
+        let Some(start) = self.cycle_start else {
+            add(0..self.inspections.len(), 1);
+            return;
+        };
+        let len = (self.inspections.len() - start) as u64;
+        let (repeats, rest) = ((rounds - start as u64) / len, (rounds - start as u64) % len);
+        add(0..start, 1);
+        add(start..self.inspections.len(), repeats);
+        add(start..start + rest as usize, 1);
+    }
+}
+
+/// How many items each monkey inspects over `rounds` rounds with worry kept modulo the
+/// product of the divisors, as in part 2. Items never meet, so each one is followed on its
+/// own until it cycles, making the work independent of `rounds`.
+pub fn inspection_counts(monkeys: &[Monkey], rounds: u64) -> Result<Vec<u64>> {
+    if let Some(monkey) = monkeys.iter().position(|monkey| !monkey.op.is_ring()) {
+        return Err(anyhow!(
+            "monkey {monkey}'s operation {} doesn't survive taking remainders",
+            monkeys[monkey].op
+        ));
+    }
+
+    let modulus = monkeys.iter().map(|m| m.throw.divisor).product::<usize>();
+    let mut counts = vec![0; monkeys.len()];
+    for (idx, monkey) in monkeys.iter().enumerate() {
+        for item in &monkey.items {
+            Trajectory::follow(monkeys, idx, *item, modulus, rounds)?.count(rounds, &mut counts);
+        }
+    }
+    Ok(counts)
+}
+
+#[test]
+fn counts_match_the_simulation() {
+    let (_, monkeys) = crate::parse_monkeys(include_str!("../input.txt")).unwrap();
+    let mut simulated = monkeys.clone();
+    crate::part2(&mut simulated).unwrap();
+    let expected: Vec<u64> = simulated
+        .iter()
+        .map(|monkey| monkey.inspection_count as u64)
+        .collect();
+
+    assert_eq!(inspection_counts(&monkeys, 10_000).unwrap(), expected);
+}
+
+#[test]
+fn counts_many_rounds() {
+    let (_, monkeys) = crate::parse_monkeys(crate::EXAMPLE).unwrap();
+    assert_eq!(
+        inspection_counts(&monkeys, 10_000).unwrap(),
+        vec![52166, 47830, 1938, 52013]
+    );
+
+    let counts = inspection_counts(&monkeys, 1_000_000_000_000).unwrap();
+    assert!(counts.iter().all(|count| *count > 100_000_000_000));
+    assert!(counts[2] < counts[0] / 10);
+}
+
+#[test]
+fn rejects_division() {
+    let (_, monkeys) = crate::parse_monkeys(
+        "Monkey 0:
+  Starting items: 1
+  Operation: new = old / 2
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0",
+    )
+    .unwrap();
+    assert!(inspection_counts(&monkeys, 5).is_err());
+}
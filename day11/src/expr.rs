@@ -0,0 +1,199 @@
+use std::fmt::{self, Debug, Display};
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::char;
+use nom::character::complete::digit1;
+use nom::character::complete::multispace0;
+use nom::combinator::map;
+use nom::combinator::map_res;
+use nom::multi::fold_many0;
+use nom::sequence::delimited;
+use nom::sequence::pair;
+use nom::sequence::preceded;
+use nom::IResult;
+
+use crate::worry::Worry;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn symbol(&self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Add | BinOp::Sub => 1,
+            BinOp::Mul | BinOp::Div => 2,
+        }
+    }
+}
+
+/// Right hand side of a monkey's `new = ...` operation.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Expr {
+    Old,
+    Num(usize),
+    Bin(Box<Expr>, BinOp, Box<Expr>),
+}
+
+impl Expr {
+    /// The new worry level, or `None` if it overflows, goes negative or divides by zero.
+    pub fn eval<W: Worry>(&self, old: &W) -> Option<W> {
+        match self {
+            Expr::Old => Some(old.clone()),
+            Expr::Num(num) => Some(W::from_usize(*num)),
+            Expr::Bin(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(old)?, rhs.eval(old)?);
+                match op {
+                    BinOp::Add => lhs.checked_add(&rhs),
+                    BinOp::Sub => lhs.checked_sub(&rhs),
+                    BinOp::Mul => lhs.checked_mul(&rhs),
+                    BinOp::Div => lhs.checked_div(&rhs),
+                }
+            }
+        }
+    }
+
+    /// Whether only `+` and `*` are used, so the worry level can be kept modulo anything.
+    pub fn is_ring(&self) -> bool {
+        match self {
+            Expr::Old | Expr::Num(_) => true,
+            Expr::Bin(lhs, op, rhs) => {
+                matches!(op, BinOp::Add | BinOp::Mul) && lhs.is_ring() && rhs.is_ring()
+            }
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Bin(_, op, _) => op.precedence(),
+            _ => u8::MAX,
+        }
+    }
+}
+
+/// Prints the puzzle's syntax, with parentheses only where they're needed.
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Num(num) => write!(f, "{num}"),
+            Expr::Bin(lhs, op, rhs) => {
+                // operators chain to the left, so a right operand of the same precedence
+                // needs wrapping too
+                if lhs.precedence() < op.precedence() {
+                    write!(f, "({lhs})")?;
+                } else {
+                    write!(f, "{lhs}")?;
+                }
+                write!(f, " {} ", op.symbol())?;
+                if rhs.precedence() <= op.precedence() {
+                    write!(f, "({rhs})")
+                } else {
+                    write!(f, "{rhs}")
+                }
+            }
+        }
+    }
+}
+
+impl Debug for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+fn parse_atom(s: &str) -> IResult<&str, Expr> {
+    delimited(
+        multispace0,
+        alt((
+            map(tag("old"), |_| Expr::Old),
+            map_res(digit1, |digit: &str| digit.parse().map(Expr::Num)),
+            delimited(char('('), parse_expr, preceded(multispace0, char(')'))),
+        )),
+        multispace0,
+    )(s)
+}
+
+fn parse_chain<'a>(
+    s: &'a str,
+    operand: fn(&'a str) -> IResult<&'a str, Expr>,
+    ops: [(char, BinOp); 2],
+) -> IResult<&'a str, Expr> {
+    let (s, first) = operand(s)?;
+    let symbol = move |s: &'a str| {
+        alt((
+            map(char(ops[0].0), |_| ops[0].1),
+            map(char(ops[1].0), |_| ops[1].1),
+        ))(s)
+    };
+
+    fold_many0(
+        pair(symbol, operand),
+        move || first.clone(),
+        |lhs, (op, rhs)| Expr::Bin(Box::new(lhs), op, Box::new(rhs)),
+    )(s)
+}
+
+fn parse_term(s: &str) -> IResult<&str, Expr> {
+    parse_chain(s, parse_atom, [('*', BinOp::Mul), ('/', BinOp::Div)])
+}
+
+/// `+ - * /` over `old` and numbers, with the usual precedence and parentheses.
+pub fn parse_expr(s: &str) -> IResult<&str, Expr> {
+    parse_chain(s, parse_term, [('+', BinOp::Add), ('-', BinOp::Sub)])
+}
+
+#[test]
+fn test_parse_expr() {
+    let (s, expr) = parse_expr("old * 11").unwrap();
+    assert_eq!(s, "");
+    assert_eq!(expr.eval(&12usize), Some(132));
+
+    let (_, expr) = parse_expr("old * old").unwrap();
+    assert_eq!(expr.eval(&12usize), Some(144));
+
+    let (_, expr) = parse_expr("(old + 2) * old / 3 - 1").unwrap();
+    assert_eq!(expr.eval(&4usize), Some(7));
+
+    let (_, expr) = parse_expr("old - 5").unwrap();
+    assert_eq!(expr.eval(&4usize), None);
+    let (_, expr) = parse_expr("old / (old - old)").unwrap();
+    assert_eq!(expr.eval(&4usize), None);
+    assert!(!expr.is_ring());
+    assert!(parse_expr("(old + 1) * old").unwrap().1.is_ring());
+}
+
+#[test]
+fn test_display_round_trips() {
+    for input in [
+        "old * 19",
+        "old + 6",
+        "old * old",
+        "(old + 2) * old / 3 - 1",
+        "old - (old - 1)",
+        "old / (2 * old)",
+        "old * 2 + old * 3",
+    ] {
+        let (_, expr) = parse_expr(input).unwrap();
+        assert_eq!(expr.to_string(), input);
+        assert_eq!(format!("{expr:?}"), input);
+    }
+
+    // redundant parentheses are dropped
+    let (_, expr) = parse_expr("((old) * (2))").unwrap();
+    assert_eq!(expr.to_string(), "old * 2");
+}
@@ -1,50 +1,89 @@
-use anyhow::Result;
-use nom::branch::alt;
+use std::fmt::{self, Display};
+
+use anyhow::{Context, Result};
 use nom::bytes::complete::tag;
-use nom::character::complete::char;
 use nom::character::complete::digit1;
 use nom::character::complete::multispace0;
 use nom::character::complete::multispace1;
 use nom::multi::separated_list0;
 use nom::sequence::tuple;
 use nom::IResult;
+use num_bigint::BigUint;
+
+mod cycles;
+mod expr;
+mod simulation;
+mod worry;
+use cycles::inspection_counts;
+use expr::{parse_expr, Expr};
+use simulation::{Outcome, Relief, Simulation};
+use worry::{Worry, WorryKind};
 
 fn main() -> Result<()> {
     let input = include_str!("../input.txt");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("rounds") = args.first().map(String::as_str) {
+        let rounds = args.get(1).context("rounds needs a count")?.parse()?;
+        return many_rounds(input, rounds);
+    }
+    if let Some("simulate") = args.first().map(String::as_str) {
+        return simulate(input, &args[1..]);
+    }
+
     let (_, mut monkeys) = parse_monkeys(input)?;
-    let part1 = part1(&mut monkeys);
+    let part1 = part1(&mut monkeys)?;
     println!("part1: {part1}");
 
     let (_, mut monkeys) = parse_monkeys(input)?;
-    let part2 = part2(&mut monkeys);
+    let part2 = part2(&mut monkeys)?;
     println!("part2: {part2}");
     Ok(())
 }
 
-fn part1(monkeys: &mut [Monkey]) -> usize {
-    for _ in 0..20 {
-        for i in 0..monkeys.len() {
-            let throws = monkeys[i].take_turn(|x| x / 3);
+/// Part 2 for any number of rounds, following each item until it cycles.
+fn many_rounds(input: &'static str, rounds: u64) -> Result<()> {
+    let (_, monkeys) = parse_monkeys(input)?;
+    let mut counts = inspection_counts(&monkeys, rounds)?;
+    for (monkey, count) in counts.iter().enumerate() {
+        println!("Monkey {monkey} inspected items {count} times.");
+    }
 
-            for (idx, item) in throws {
-                monkeys[idx].add_item(item)
-            }
-        }
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    let business: u128 = counts.iter().take(2).map(|count| *count as u128).product();
+    println!("monkey business: {business}");
+    Ok(())
+}
+
+/// Runs the monkeys with the settings given as flags, printing the requested reports.
+fn simulate(input: &'static str, args: &[String]) -> Result<()> {
+    let simulation = Simulation::parse(args)?;
+    let (_, monkeys) = parse_monkeys(input)?;
+    match simulation.worry {
+        WorryKind::Usize => show(simulation.run(&mut monkeys.clone())?),
+        WorryKind::U128 => show(simulation.run(&mut with_worry::<u128>(monkeys))?),
+        WorryKind::Big => show(simulation.run(&mut with_worry::<BigUint>(monkeys))?),
     }
+    Ok(())
+}
 
-    monkeys.sort_by_key(|monkey| monkey.inspection_count);
-    monkeys.reverse();
+fn with_worry<W: Worry>(monkeys: Vec<Monkey>) -> Vec<Monkey<W>> {
+    monkeys.into_iter().map(Monkey::with_worry).collect()
+}
 
-    monkeys
-        .iter()
-        .take(2)
-        .map(|monkey| monkey.inspection_count)
-        .product()
+fn show<W: Worry>(outcome: Outcome<W>) {
+    for report in &outcome.reports {
+        println!("{report}");
+    }
+    println!("monkey business: {}", outcome.monkey_business);
 }
 
-#[test]
-fn part2_works() {
-    let input = r#"Monkey 0:
+fn part1(monkeys: &mut [Monkey]) -> Result<u128> {
+    Ok(Simulation::default().run(monkeys)?.monkey_business)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = r#"Monkey 0:
   Starting items: 79, 98
   Operation: new = old * 19
   Test: divisible by 23
@@ -72,58 +111,88 @@ Monkey 3:
     If true: throw to monkey 0
     If false: throw to monkey 1"#;
 
-    let (_, mut monkeys) = parse_monkeys(input).unwrap();
-    let part2 = part2(&mut monkeys);
+#[test]
+fn part2_works() {
+    let (_, mut monkeys) = parse_monkeys(EXAMPLE).unwrap();
+    let part2 = part2(&mut monkeys).unwrap();
     assert_eq!(part2, 2713310158);
 }
 
-fn part2(monkeys: &mut [Monkey]) -> usize {
-    let lcd = monkeys.iter().map(|m| m.test_num).product::<usize>();
-    for _ in 0..10_000 {
-        for i in 0..monkeys.len() {
-            let throws = monkeys[i].take_turn(|x| x % lcd);
+fn part2(monkeys: &mut [Monkey]) -> Result<u128> {
+    let outcome = Simulation::default()
+        .with_rounds(10_000)
+        .with_relief(Relief::Modulo)
+        .run(monkeys)?;
+    Ok(outcome.monkey_business)
+}
+
+/// Where a monkey throws an item after testing its worry level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Throw {
+    divisor: usize,
+    if_true: usize,
+    if_false: usize,
+}
 
-            for (idx, item) in throws {
-                monkeys[idx].add_item(item)
-            }
+impl Throw {
+    fn target<W: Worry>(&self, worry: &W) -> usize {
+        if worry.rem_usize(self.divisor) == 0 {
+            self.if_true
+        } else {
+            self.if_false
         }
     }
+}
 
-    monkeys.sort_by_key(|monkey| monkey.inspection_count);
-    monkeys.reverse();
-
-    monkeys
-        .iter()
-        .take(2)
-        .map(|monkey| monkey.inspection_count)
-        .product()
+impl Display for Throw {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Test: divisible by {}", self.divisor)?;
+        writeln!(f, "    If true: throw to monkey {}", self.if_true)?;
+        write!(f, "    If false: throw to monkey {}", self.if_false)
+    }
 }
 
-struct Monkey {
-    items: Vec<usize>,
-    test_num: usize,
-    op: Box<dyn Fn(usize) -> Option<usize>>,
-    throw: Box<dyn Fn(usize) -> usize>,
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Monkey<W = usize> {
+    items: Vec<W>,
+    op: Expr,
+    throw: Throw,
     inspection_count: usize,
 }
 
 impl Monkey {
-    fn take_turn(&mut self, reduction: impl Fn(usize) -> usize) -> Vec<(usize, usize)> {
+    /// The same monkey tracking worry levels in another type.
+    fn with_worry<W: Worry>(self) -> Monkey<W> {
+        Monkey {
+            items: self.items.into_iter().map(W::from_usize).collect(),
+            op: self.op,
+            throw: self.throw,
+            inspection_count: self.inspection_count,
+        }
+    }
+}
+
+impl<W: Worry> Monkey<W> {
+    fn take_turn(&mut self, reduction: impl Fn(W) -> Option<W>) -> Result<Vec<(usize, W)>> {
         let items = std::mem::take(&mut self.items);
 
         items
             .into_iter()
             .map(|item| {
                 self.inspection_count += 1;
-                let worry = (reduction)((self.op)(item).expect("Shouldn't overflow"));
-
-                let next_monkey = (self.throw)(worry);
-                (next_monkey, worry)
+                let worry = self
+                    .op
+                    .eval(&item)
+                    .and_then(&reduction)
+                    .with_context(|| format!("{} doesn't fit for old = {item}", self.op))?;
+
+                let next_monkey = self.throw.target(&worry);
+                Ok((next_monkey, worry))
             })
             .collect()
     }
 
-    fn add_item(&mut self, item: usize) {
+    fn add_item(&mut self, item: W) {
         self.items.push(item)
     }
 }
@@ -170,50 +239,18 @@ fn parse_items(s: &str) -> IResult<&str, Vec<usize>> {
     Ok((s, items))
 }
 
-#[test]
-fn test_parse_operator() {
-    let input = "* 11";
-    let (_, result_op) = parse_operator(input).unwrap();
-    assert_eq!((result_op)(12).unwrap(), 132);
-
-    let input = "* old";
-    let (_, result_op) = parse_operator(input).unwrap();
-    assert_eq!((result_op)(12).unwrap(), 144)
-}
-
-fn parse_operator(s: &str) -> IResult<&str, Box<dyn Fn(usize) -> Option<usize>>> {
-    let (s, (operator, _, digit)) = tuple((
-        alt((char('*'), char('+'))),
-        multispace0,
-        alt((digit1, tag("old"))),
-    ))(s)?;
-
-    let op = match operator {
-        '*' => usize::checked_mul,
-        '+' => usize::checked_add,
-        _ => unreachable!("Unknown operator"),
-    };
-
-    let func: Box<dyn Fn(usize) -> Option<usize>> = if let Ok(digit) = digit.parse::<usize>() {
-        Box::new(move |other: usize| (op)(digit, other))
-    } else {
-        Box::new(move |other: usize| (op)(other, other))
-    };
-
-    Ok((s, func))
-}
-
 #[test]
 fn test_parse_operation() {
     let input = " Operation: new = old * 11";
-    let (_, result_op) = parse_operation(input).unwrap();
-    assert_eq!((result_op)(12).unwrap(), 132)
+    let (_, op) = parse_operation(input).unwrap();
+    assert_eq!(op.eval(&12usize), Some(132));
+    assert_eq!(op.to_string(), "old * 11");
 }
 
-fn parse_operation(s: &str) -> IResult<&str, Box<dyn Fn(usize) -> Option<usize>>> {
-    let (s, (_, _, op)) = tuple((multispace0, tag("Operation: new = old "), parse_operator))(s)?;
+fn parse_operation(s: &str) -> IResult<&str, Expr> {
+    let (s, (_, _, op)) = tuple((multispace0, tag("Operation: new = "), parse_expr))(s)?;
 
-    Ok((s, Box::new(op)))
+    Ok((s, op))
 }
 
 fn parse_condition(s: &str) -> IResult<&str, usize> {
@@ -259,18 +296,24 @@ fn test_parse_throw() {
     If true: throw to monkey 7
     If false: throw to monkey 1
 "#;
-    let (_, (_, result_op)) = parse_throw(input).unwrap();
-    assert_eq!((result_op)(12), 7);
-    assert_eq!((result_op)(13), 1);
+    let (_, throw) = parse_throw(input).unwrap();
+    assert_eq!(throw.target(&12usize), 7);
+    assert_eq!(throw.target(&13usize), 1);
+    assert_eq!(format!("  {throw}\n"), input.replacen(' ', "  ", 1));
 }
 
-fn parse_throw(s: &str) -> IResult<&str, (usize, Box<dyn Fn(usize) -> usize>)> {
-    let (s, (_, cond, is_on, is_off)) =
+fn parse_throw(s: &str) -> IResult<&str, Throw> {
+    let (s, (_, divisor, if_true, if_false)) =
         tuple((multispace0, parse_condition, parse_on, parse_off))(s)?;
 
-    let func = move |other| if other % cond == 0 { is_on } else { is_off };
-
-    Ok((s, (cond, Box::new(func))))
+    Ok((
+        s,
+        Throw {
+            divisor,
+            if_true,
+            if_false,
+        },
+    ))
 }
 
 #[test]
@@ -285,7 +328,7 @@ fn test_parse_monkey() {
 }
 
 fn parse_monkey(s: &str) -> IResult<&str, Monkey> {
-    let (s, (_, items, op, (test_num, throw))) =
+    let (s, (_, items, op, throw)) =
         tuple((parse_monkey_id, parse_items, parse_operation, parse_throw))(s)?;
 
     Ok((
@@ -293,7 +336,6 @@ fn parse_monkey(s: &str) -> IResult<&str, Monkey> {
         Monkey {
             items,
             op,
-            test_num,
             throw,
             inspection_count: 0,
         },
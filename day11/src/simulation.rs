@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context, Error, Result};
+
+use crate::{
+    worry::{Worry, WorryKind},
+    Monkey,
+};
+
+/// How worry levels are kept in check after each inspection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relief {
+    /// Divided by the number, rounding down, as in part 1.
+    Divide(usize),
+    /// Kept modulo the product of the monkeys' divisors, as in part 2.
+    Modulo,
+    /// Left alone, to see the exact worry levels.
+    None,
+}
+
+impl Relief {
+    fn apply<W: Worry>(&self, worry: W, modulus: usize) -> Option<W> {
+        match self {
+            Relief::Divide(by) => worry.checked_div(&W::from_usize(*by)),
+            Relief::Modulo => Some(W::from_usize(worry.rem_usize(modulus))),
+            Relief::None => Some(worry),
+        }
+    }
+}
+
+impl FromStr for Relief {
+    type Err = Error;
+
+    /// A number to divide by, `lcm` or `none`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lcm" => Ok(Relief::Modulo),
+            "none" => Ok(Relief::None),
+            _ => match s.parse().context(format!("unknown relief {s}"))? {
+                0 => Err(anyhow!("can't divide worry by 0")),
+                by => Ok(Relief::Divide(by)),
+            },
+        }
+    }
+}
+
+/// The monkeys' state after a round.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report<W> {
+    pub round: usize,
+    pub items: Vec<Vec<W>>,
+    pub inspections: Vec<usize>,
+}
+
+/// Written the way the puzzle narrates the rounds.
+impl<W: Display> Display for Report<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== After round {} ==", self.round)?;
+        for (monkey, items) in self.items.iter().enumerate() {
+            let items: Vec<String> = items.iter().map(W::to_string).collect();
+            write!(f, "Monkey {monkey}:")?;
+            if !items.is_empty() {
+                write!(f, " {}", items.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+        for (monkey, count) in self.inspections.iter().enumerate() {
+            writeln!(f, "Monkey {monkey} inspected items {count} times.")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome<W> {
+    /// Product of the `top` biggest inspection counts.
+    pub monkey_business: u128,
+    pub reports: Vec<Report<W>>,
+}
+
+/// Settings for a run of the monkeys, part 1's unless changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Simulation {
+    pub rounds: usize,
+    pub relief: Relief,
+    /// How many of the busiest monkeys make up the monkey business.
+    pub top: usize,
+    /// Rounds to report on.
+    pub reports: BTreeSet<usize>,
+    /// Only used to pick the type when running from the command line.
+    pub worry: WorryKind,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            rounds: 20,
+            relief: Relief::Divide(3),
+            top: 2,
+            reports: BTreeSet::new(),
+            worry: WorryKind::default(),
+        }
+    }
+}
+
+impl Simulation {
+    pub fn with_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    pub fn with_relief(mut self, relief: Relief) -> Self {
+        self.relief = relief;
+        self
+    }
+
+    pub fn with_top(mut self, top: usize) -> Self {
+        self.top = top;
+        self
+    }
+
+    pub fn with_reports(mut self, rounds: impl IntoIterator<Item = usize>) -> Self {
+        self.reports.extend(rounds);
+        self
+    }
+
+    pub fn with_worry(mut self, worry: WorryKind) -> Self {
+        self.worry = worry;
+        self
+    }
+
+    /// Parses `--rounds N`, `--relief N|lcm|none`, `--top K`, `--report R1,R2,...` and
+    /// `--worry usize|u128|big`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut simulation = Simulation::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            let value = args.next().context(format!("{flag} needs a value"))?;
+            simulation = match flag.as_str() {
+                "--rounds" => simulation.with_rounds(value.parse()?),
+                "--relief" => simulation.with_relief(value.parse()?),
+                "--top" => simulation.with_top(value.parse()?),
+                "--worry" => simulation.with_worry(value.parse()?),
+                "--report" => simulation.with_reports(
+                    value
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+                _ => return Err(anyhow!("unknown flag {flag}")),
+            };
+        }
+
+        Ok(simulation)
+    }
+
+    pub fn run<W: Worry>(&self, monkeys: &mut [Monkey<W>]) -> Result<Outcome<W>> {
+        if self.relief == Relief::Modulo {
+            if let Some(monkey) = monkeys.iter().position(|monkey| !monkey.op.is_ring()) {
+                return Err(anyhow!(
+                    "monkey {monkey}'s operation {} doesn't survive taking remainders",
+                    monkeys[monkey].op
+                ));
+            }
+        }
+        let modulus = monkeys.iter().map(|m| m.throw.divisor).product::<usize>();
+        let mut reports = vec![];
+
+        for round in 1..=self.rounds {
+            for i in 0..monkeys.len() {
+                let throws = monkeys[i]
+                    .take_turn(|x| self.relief.apply(x, modulus))
+                    .with_context(|| format!("monkey {i} in round {round}"))?;
+
+                for (idx, item) in throws {
+                    monkeys[idx].add_item(item)
+                }
+            }
+
+            if self.reports.contains(&round) {
+                reports.push(Report {
+                    round,
+                    items: monkeys.iter().map(|m| m.items.clone()).collect(),
+                    inspections: monkeys.iter().map(|m| m.inspection_count).collect(),
+                });
+            }
+        }
+
+        let mut counts: Vec<usize> = monkeys.iter().map(|m| m.inspection_count).collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        let monkey_business = counts
+            .iter()
+            .take(self.top)
+            .try_fold(1u128, |product, count| product.checked_mul(*count as u128))
+            .with_context(|| format!("monkey business of the top {} overflows", self.top))?;
+        Ok(Outcome {
+            monkey_business,
+            reports,
+        })
+    }
+}
+
+#[test]
+fn reports_match_the_puzzle() {
+    let (_, mut monkeys) = crate::parse_monkeys(crate::EXAMPLE).unwrap();
+    let outcome = Simulation::default()
+        .with_reports([1, 20])
+        .run(&mut monkeys)
+        .unwrap();
+
+    assert_eq!(outcome.monkey_business, 10605);
+    assert_eq!(
+        outcome.reports[0].to_string(),
+        "== After round 1 ==
+Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2:
+Monkey 3:
+
+Monkey 0 inspected items 2 times.
+Monkey 1 inspected items 4 times.
+Monkey 2 inspected items 3 times.
+Monkey 3 inspected items 5 times.
+"
+    );
+    assert_eq!(outcome.reports[1].inspections, vec![101, 95, 7, 105]);
+}
+
+#[test]
+fn parameters_change_the_outcome() {
+    let (_, mut monkeys) = crate::parse_monkeys(crate::EXAMPLE).unwrap();
+    let args: Vec<String> = "--rounds 1000 --relief lcm --top 3 --report 1000"
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    let outcome = Simulation::parse(&args).unwrap().run(&mut monkeys).unwrap();
+
+    assert_eq!(outcome.reports[0].inspections, vec![5204, 4792, 199, 5192]);
+    assert_eq!(outcome.monkey_business, 5204 * 5192 * 4792);
+
+    assert!("0".parse::<Relief>().is_err());
+    assert!("half".parse::<Relief>().is_err());
+}
+
+#[test]
+fn exact_worry_needs_big_numbers() {
+    let (_, monkeys) = crate::parse_monkeys(crate::EXAMPLE).unwrap();
+    let exact = Simulation::default()
+        .with_rounds(40)
+        .with_relief(Relief::None)
+        .with_reports([40]);
+
+    let err = exact.run(&mut monkeys.clone()).unwrap_err();
+    assert_eq!(
+        format!("{err:#}"),
+        "monkey 0 in round 13: old * 19 doesn't fit for old = 13988703546165100909"
+    );
+    let err = exact
+        .run(&mut crate::with_worry::<u128>(monkeys.clone()))
+        .unwrap_err();
+    assert!(err.to_string().starts_with("monkey 0 in round 31"));
+
+    let big = exact
+        .run(&mut crate::with_worry::<crate::BigUint>(monkeys.clone()))
+        .unwrap();
+    // keeping worry modulo the divisors' product doesn't change where items go
+    let modular = exact
+        .clone()
+        .with_relief(Relief::Modulo)
+        .run(&mut monkeys.clone())
+        .unwrap();
+    assert_eq!(big.reports[0].inspections, modular.reports[0].inspections);
+    assert!(big.reports[0]
+        .items
+        .iter()
+        .flatten()
+        .any(|w| w.to_string().len() > 50));
+}
+
+#[test]
+fn top_can_cover_every_monkey() {
+    let (_, mut monkeys) = crate::parse_monkeys(crate::EXAMPLE).unwrap();
+    let args: Vec<String> = "--rounds 10000 --relief lcm --top 4"
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    let outcome = Simulation::parse(&args).unwrap().run(&mut monkeys).unwrap();
+    assert_eq!(outcome.monkey_business, 52166 * 47830 * 1938 * 52013);
+
+    // the input's eight monkeys inspect too much for even 128 bits
+    let (_, monkeys) = crate::parse_monkeys(include_str!("../input.txt")).unwrap();
+    let simulation = Simulation::default()
+        .with_rounds(10_000)
+        .with_relief(Relief::Modulo);
+
+    let all = simulation
+        .clone()
+        .with_top(4)
+        .run(&mut monkeys.clone())
+        .unwrap();
+    assert!(all.monkey_business > usize::MAX as u128);
+
+    let err = simulation
+        .with_top(monkeys.len())
+        .run(&mut monkeys.clone())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "monkey business of the top 8 overflows");
+}
+
+#[test]
+fn modulo_relief_needs_a_ring() {
+    let (_, mut monkeys) = crate::parse_monkeys(
+        "Monkey 0:
+  Starting items: 4
+  Operation: new = old / 2
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0",
+    )
+    .unwrap();
+    let modulo = Simulation::default().with_relief(Relief::Modulo);
+    let err = modulo.run(&mut monkeys.clone()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "monkey 0's operation old / 2 doesn't survive taking remainders"
+    );
+    assert!(Simulation::default().run(&mut monkeys).is_ok());
+}
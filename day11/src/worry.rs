@@ -0,0 +1,122 @@
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Error, Result};
+
+use num_bigint::BigUint;
+use num_traits::{CheckedDiv, CheckedSub, ToPrimitive};
+
+/// Number type that worry levels are tracked in. Arithmetic that doesn't fit gives `None`.
+pub trait Worry: Clone + Debug + Display + Eq {
+    fn from_usize(value: usize) -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn checked_div(&self, other: &Self) -> Option<Self>;
+    fn rem_usize(&self, modulus: usize) -> usize;
+}
+
+macro_rules! impl_worry {
+    ($($t:ty),*) => {$(
+        impl Worry for $t {
+            fn from_usize(value: usize) -> Self {
+                value as $t
+            }
+
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_sub(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_sub(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *other)
+            }
+
+            fn checked_div(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_div(*self, *other)
+            }
+
+            fn rem_usize(&self, modulus: usize) -> usize {
+                (*self % modulus as $t) as usize
+            }
+        }
+    )*};
+}
+
+impl_worry!(usize, u128);
+
+/// Never overflows, but only gives up on going negative or dividing by zero.
+impl Worry for BigUint {
+    fn from_usize(value: usize) -> Self {
+        BigUint::from(value)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        CheckedSub::checked_sub(self, other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        CheckedDiv::checked_div(self, other)
+    }
+
+    fn rem_usize(&self, modulus: usize) -> usize {
+        (self % modulus)
+            .to_usize()
+            .expect("remainder is smaller than the modulus")
+    }
+}
+
+#[test]
+fn big_worry_matches_u128() {
+    let values: [u128; 5] = [0, 1, 3, 4_294_967_295, 18_446_744_073_709_551_557];
+    for a in values {
+        for b in values {
+            let (big_a, big_b) = (BigUint::from(a), BigUint::from(b));
+            let check = |big: Option<BigUint>, small: Option<u128>| {
+                assert_eq!(big, small.map(BigUint::from), "{a} and {b}")
+            };
+            check(Worry::checked_add(&big_a, &big_b), a.checked_add(b));
+            check(Worry::checked_sub(&big_a, &big_b), a.checked_sub(b));
+            check(Worry::checked_mul(&big_a, &big_b), a.checked_mul(b));
+            check(Worry::checked_div(&big_a, &big_b), a.checked_div(b));
+        }
+        assert_eq!(BigUint::from(a).rem_usize(97), (a % 97) as usize);
+    }
+}
+
+/// Which [`Worry`] type to simulate with, picked at runtime.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorryKind {
+    #[default]
+    Usize,
+    U128,
+    Big,
+}
+
+impl FromStr for WorryKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "usize" => Ok(WorryKind::Usize),
+            "u128" => Ok(WorryKind::U128),
+            "big" => Ok(WorryKind::Big),
+            _ => Err(anyhow!(
+                "unknown worry type {s}, expected usize, u128 or big"
+            )),
+        }
+    }
+}
@@ -1,18 +1,26 @@
-use anyhow::{Context, Error, Result};
+use anyhow::{anyhow, Context, Error, Result};
 use petgraph::algo::astar;
 use petgraph::prelude::*;
 use petgraph::Graph;
-use rayon::prelude::*;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::{collections::HashMap, str::FromStr};
 
+mod render;
+mod rules;
+use render::{render, Style};
+use rules::Rules;
+
 #[derive(Debug)]
 struct Map {
-    inner: Graph<i32, ()>,
+    /// Cells indexed by [`coord_into_u32`], with an edge for every allowed move.
+    inner: Graph<i32, u32>,
     start: Coord,
     end: Coord,
     x_max: usize,
     starting_points: Vec<Coord>,
+    /// The heightmap as given, `S` and `E` included.
+    rows: Vec<Vec<char>>,
 }
 
 type Coord = (isize, isize);
@@ -21,7 +29,13 @@ impl FromStr for Map {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let y_max = s.lines().count();
+        Map::new(s, &Rules::default())
+    }
+}
+
+impl Map {
+    /// Builds the graph of moves allowed by `rules`, each weighted by its cost.
+    fn new(s: &str, rules: &Rules) -> Result<Self> {
         let x_max = s
             .lines()
             .next()
@@ -71,7 +85,7 @@ impl FromStr for Map {
                         'S' => 'a',
                         _ => c,
                     };
-                    (c == 'a').then(|| (x as isize, y as isize))
+                    (c == 'a').then_some((x as isize, y as isize))
                 })
             })
             .collect();
@@ -79,20 +93,20 @@ impl FromStr for Map {
         let edges = map
             .iter()
             .flat_map(|(coord, c)| {
-                [(0, 1), (0, -1), (1, 0), (-1, 0)]
-                    .iter()
-                    .filter_map(|offset| {
-                        let new = (coord.0 + offset.0, coord.1 + offset.1);
-                        let Some(other) = map.get(&new) else { return None };
-                        let diff = (*c as u8) as i8 - ((*other as u8) as i8);
-                        (diff >= -1).then_some({
-                            (coord_into_u32(coord, x_max), coord_into_u32(&new, x_max))
-                        })
-                    })
+                rules.offsets().iter().filter_map(|offset| {
+                    let new = (coord.0 + offset.0, coord.1 + offset.1);
+                    let other = map.get(&new)?;
+                    let cost = rules.cost(*other as i32 - *c as i32)?;
+                    Some((
+                        coord_into_u32(coord, x_max),
+                        coord_into_u32(&new, x_max),
+                        cost,
+                    ))
+                })
             })
             .collect::<Vec<_>>();
 
-        let inner = Graph::<i32, ()>::from_edges(edges);
+        let inner = Graph::<i32, u32>::from_edges(edges);
 
         Ok(Self {
             inner,
@@ -100,69 +114,119 @@ impl FromStr for Map {
             end,
             x_max,
             starting_points,
+            rows: s.lines().map(|line| line.chars().collect()).collect(),
         })
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Route {
+    cost: u32,
+    /// Every cell from the start to `E`, both included.
+    cells: Vec<Coord>,
+}
+
+/// Cost of the cheapest route to `E` from each cell, indexed like the graph's nodes.
+struct Distances {
+    costs: Vec<Option<u32>>,
+    x_max: usize,
+}
+
+impl Distances {
+    /// `None` for cells off the map or that can't reach `E`.
+    fn get(&self, coord: &Coord) -> Option<u32> {
+        let in_map = coord.0 >= 0 && coord.1 >= 0 && (coord.0 as usize) < self.x_max;
+        let idx = coord_into_u32(coord, self.x_max) as usize;
+        in_map.then(|| self.costs.get(idx).copied().flatten())?
+    }
+}
+
 fn coord_into_u32(coord: &Coord, x_max: usize) -> u32 {
     (coord.0 as usize + coord.1 as usize * x_max) as u32
 }
 
 impl Map {
-    fn find_shortest_path(&self) -> Option<u32> {
+    /// Cheapest route from `S` to `E`.
+    fn find_shortest_path(&self) -> Option<Route> {
         let start = self.coord_into_u32(&self.start);
         let end = self.coord_into_u32(&self.end);
-        let (step, path) = astar(
+        let (cost, path) = astar(
             &self.inner,
             start.into(),
             |finish| finish == end.into(),
-            |_| 1,
-            |_| 1,
+            |edge| *edge.weight(),
+            |_| 0,
         )?;
-        // let mut step_map = HashMap::new();
-        // let mut y_max = 0;
-
-        // for (i, p) in path.iter().enumerate() {
-        //     let coord = self.u32_into_coord(p.index() as u32);
-        //     if coord.1 > y_max {
-        //         y_max = coord.1
-        //     };
-        //     step_map.insert(coord, i);
-        // }
-        //
-        // for y in 0..y_max + 1 {
-        //     for x in 0..self.x_max {
-        //         if let Some(count) = step_map.get(&(x as isize, y)) {
-        //             print!("{:02} ", count);
-        //         } else {
-        //             print!(".. ");
-        //         }
-        //     }
-        //     println!()
-        // }
-        //
-        Some(step)
+        Some(Route {
+            cost,
+            cells: path
+                .into_iter()
+                .map(|node| self.u32_into_coord(node.index() as u32))
+                .collect(),
+        })
     }
 
-    fn find_shortest_path_all_a(&self) -> Option<u32> {
-        let end = self.coord_into_u32(&self.end);
-
-        let steps = self
+    /// Cheapest route to `E` from whichever `a` is closest.
+    fn find_shortest_path_all_a(&self) -> Option<Route> {
+        let distances = self.distances_to_end();
+        let mut current = *self
             .starting_points
-            .par_iter()
-            .filter_map(|start| {
-                let Some((step, _)) = astar(
-                &self.inner,
-                self.coord_into_u32(start).into(),
-                |finish| finish == end.into(),
-                |_| 1,
-                |_| 1,
-            ) else { return None };
-                Some(step)
-            })
-            .collect::<Vec<_>>();
+            .iter()
+            .filter(|start| distances.get(start).is_some())
+            .min_by_key(|start| distances.get(start))?;
+
+        // every cell but `E` has a move to a cell as much cheaper as the move costs
+        let mut cells = vec![current];
+        while let Some(cost) = distances.get(&current).filter(|cost| *cost > 0) {
+            let node = NodeIndex::new(self.coord_into_u32(&current) as usize);
+            current = self
+                .inner
+                .edges(node)
+                .map(|edge| {
+                    (
+                        self.u32_into_coord(edge.target().index() as u32),
+                        edge.weight(),
+                    )
+                })
+                .find(|(next, weight)| {
+                    distances.get(next).map(|rest| rest + *weight) == Some(cost)
+                })?
+                .0;
+            cells.push(current);
+        }
 
-        steps.into_iter().min()
+        Some(Route {
+            cost: distances.get(&cells[0])?,
+            cells,
+        })
+    }
+
+    /// Cost from every cell to `E`, found by one Dijkstra search walking the moves
+    /// backwards from `E`. With unit costs this is a plain breadth first search.
+    fn distances_to_end(&self) -> Distances {
+        let end = self.coord_into_u32(&self.end) as usize;
+        let mut costs = vec![None; self.inner.node_count()];
+        let mut queue = BinaryHeap::from([Reverse((0, end))]);
+
+        while let Some(Reverse((cost, node))) = queue.pop() {
+            match costs.get_mut(node) {
+                Some(slot @ None) => *slot = Some(cost),
+                _ => continue,
+            }
+            for edge in self
+                .inner
+                .edges_directed(NodeIndex::new(node), Direction::Incoming)
+            {
+                if costs[edge.source().index()].is_none() {
+                    queue.push(Reverse((cost + edge.weight(), edge.source().index())));
+                }
+            }
+        }
+
+        Distances {
+            costs,
+            x_max: self.x_max,
+        }
     }
 
     fn coord_into_u32(&self, coord: &Coord) -> u32 {
@@ -178,13 +242,51 @@ impl Map {
 
 fn main() -> Result<()> {
     let input = include_str!("../input.txt");
-    let map = input.parse::<Map>()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, flags) = match args.first().map(String::as_str) {
+        Some("route") => (Some("route"), &args[1..]),
+        _ => (None, &args[..]),
+    };
+
+    // everything that isn't the route's own argument is a climbing rule
+    let (route_args, rule_args): (Vec<String>, Vec<String>) = flags
+        .iter()
+        .cloned()
+        .partition(|arg| command.is_some() && (arg == "a" || arg == "--steps"));
+    let map = Map::new(input, &Rules::parse(&rule_args)?)?;
+
+    if command.is_some() {
+        return route(&map, &route_args);
+    }
+
     let part1 = map.find_shortest_path().context("Couldn't get path")?;
-    println!("part1: {part1}");
+    println!("part1: {}", part1.cost);
     let part2 = map
         .find_shortest_path_all_a()
         .context("Couldn't get path for part2")?;
-    println!("part2: {part2}");
+    println!("part2: {}", part2.cost);
+    Ok(())
+}
+
+/// Draws the route from `S`, or from the best `a` when given `a`. `--steps` numbers the
+/// route instead of drawing arrows.
+fn route(map: &Map, args: &[String]) -> Result<()> {
+    let (mut from_a, mut style) = (false, Style::Arrows);
+    for arg in args {
+        match arg.as_str() {
+            "a" => from_a = true,
+            "--steps" => style = Style::Steps,
+            _ => return Err(anyhow!("unknown argument {arg}")),
+        }
+    }
+
+    let route = match from_a {
+        true => map.find_shortest_path_all_a(),
+        false => map.find_shortest_path(),
+    }
+    .context("no route to E")?;
+    print!("{}", render(map, &route.cells, style));
+    println!("{} steps costing {}", route.cells.len() - 1, route.cost);
     Ok(())
 }
 
@@ -196,6 +298,61 @@ accszExk
 acctuvwj
 abdefghi"#;
     let map = input.parse::<Map>().unwrap();
-    let part1 = map.find_shortest_path();
-    assert_eq!(part1, Some(31));
+    let part1 = map.find_shortest_path().unwrap();
+    assert_eq!((part1.cost, part1.cells.len() - 1), (31, 31));
+    let part2 = map.find_shortest_path_all_a().unwrap();
+    assert_eq!((part2.cost, part2.cells.len() - 1), (29, 29));
+}
+
+#[test]
+fn distances_cover_every_cell() {
+    let input = r#"Sabqponm
+abcryxxl
+accszExk
+acctuvwj
+abdefghi"#;
+    let map = input.parse::<Map>().unwrap();
+    let distances = map.distances_to_end();
+    assert_eq!(distances.get(&map.start), Some(31));
+    assert_eq!(distances.get(&map.end), Some(0));
+    assert_eq!(distances.get(&(4, 2)), Some(1));
+    assert_eq!(distances.get(&(8, 0)), None);
+    assert_eq!(distances.get(&(-1, 0)), None);
+}
+
+#[test]
+fn rules_change_the_routes() {
+    let input = r#"Sabqponm
+abcryxxl
+accszExk
+acctuvwj
+abdefghi"#;
+    let cost = |args: &str| {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        let map = Map::new(input, &Rules::parse(&args).unwrap()).unwrap();
+        let from_s = map.find_shortest_path().map(|route| route.cost);
+        let from_a = map.find_shortest_path_all_a().map(|route| route.cost);
+        // both searches agree on what the cheapest route from `S` costs
+        assert_eq!(map.distances_to_end().get(&map.start), from_s);
+        (from_s, from_a)
+    };
+
+    assert_eq!(cost(""), (Some(31), Some(29)));
+    assert_eq!(cost("--diagonal"), (Some(27), Some(26)));
+    assert_eq!(cost("--ascent 2"), (Some(27), Some(23)));
+    assert_eq!(cost("--ascent 0"), (None, None));
+    assert_eq!(cost("--cost climb"), (Some(56), Some(54)));
+    assert_eq!(cost("--diagonal --cost steep"), (Some(52), Some(51)));
+
+    // the only way to `E` drops three levels before the final climb
+    let rules = |descent| Rules {
+        max_ascent: 25,
+        max_descent: descent,
+        ..Rules::default()
+    };
+    let map = Map::new("SbdaE", &rules(None)).unwrap();
+    assert_eq!(map.find_shortest_path().map(|route| route.cost), Some(4));
+    let map = Map::new("SbdaE", &rules(Some(2))).unwrap();
+    assert_eq!(map.find_shortest_path(), None);
+    assert!(Rules::parse(&["--cost".into(), "free".into()]).is_err());
 }
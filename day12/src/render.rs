@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::{Coord, Map};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Which way the route leaves each cell, as in the puzzle.
+    Arrows,
+    /// How many steps into the route each cell is.
+    Steps,
+}
+
+fn arrow(from: Coord, to: Coord) -> char {
+    match (to.0 - from.0, to.1 - from.1) {
+        (1, 0) => '>',
+        (-1, 0) => '<',
+        (0, -1) => '^',
+        (0, 1) => 'v',
+        (1, -1) | (-1, 1) => '/',
+        (1, 1) | (-1, -1) => '\\',
+        _ => '*',
+    }
+}
+
+/// The heightmap with `route` drawn over it.
+pub fn render(map: &Map, route: &[Coord], style: Style) -> String {
+    let marks: HashMap<Coord, String> = match style {
+        Style::Arrows => route
+            .windows(2)
+            .map(|pair| (pair[0], arrow(pair[0], pair[1]).to_string()))
+            .collect(),
+        Style::Steps => {
+            let width = route.len().saturating_sub(1).to_string().len();
+            route
+                .iter()
+                .enumerate()
+                .map(|(step, coord)| (*coord, format!("{step:0width$}")))
+                .collect()
+        }
+    };
+    let width = marks.values().map(String::len).max().unwrap_or(1);
+
+    map.rows
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(x, height)| match marks.get(&(x as isize, y as isize)) {
+                    Some(mark) => mark.clone(),
+                    None => format!("{height:>width$}"),
+                })
+                .collect();
+            let separator = if width > 1 { " " } else { "" };
+            cells.join(separator) + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Sabqponm
+abcryxxl
+accszExk
+acctuvwj
+abdefghi";
+
+#[test]
+fn arrows_follow_the_route() {
+    let map: Map = EXAMPLE.parse().unwrap();
+    let route = map.find_shortest_path().unwrap().cells;
+    assert_eq!(route.first(), Some(&map.start));
+    assert_eq!(route.last(), Some(&map.end));
+
+    let drawn = render(&map, &route, Style::Arrows);
+    let arrows = drawn.chars().filter(|c| "<>^v".contains(*c)).count();
+    assert_eq!(arrows, 31);
+    assert_eq!(drawn.lines().nth(2).unwrap().chars().nth(5), Some('E'));
+    assert!(drawn.starts_with('v') || drawn.starts_with('>'));
+}
+
+#[test]
+fn steps_number_the_best_a_route() {
+    let map: Map = EXAMPLE.parse().unwrap();
+    let route = map.find_shortest_path_all_a().unwrap().cells;
+    assert_eq!(route.len(), 30);
+    // every step is to a neighbour
+    assert!(route.windows(2).all(|pair| arrow(pair[0], pair[1]) != '*'));
+
+    let drawn = render(&map, &route, Style::Steps);
+    assert_eq!(drawn.lines().count(), 5);
+    assert!(drawn.lines().all(|line| line.len() == 8 * 3 - 1));
+    assert!(drawn.contains("29"));
+    assert!(drawn.contains(" S"));
+}
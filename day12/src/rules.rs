@@ -0,0 +1,101 @@
+use anyhow::{anyhow, Context, Result};
+
+/// What a move between neighbouring cells costs, given how far it climbs (negative going
+/// down).
+#[derive(Debug, Clone, Copy)]
+pub enum Cost {
+    /// Every move costs 1, as in the puzzle.
+    Unit,
+    /// 1 plus the levels climbed, descending is as cheap as walking on the flat.
+    Climb,
+    /// 1 plus the levels climbed or descended.
+    Steepness,
+}
+
+impl Cost {
+    pub fn of(&self, climb: i32) -> u32 {
+        match self {
+            Cost::Unit => 1,
+            Cost::Climb => 1 + climb.max(0) as u32,
+            Cost::Steepness => 1 + climb.unsigned_abs(),
+        }
+    }
+}
+
+/// How the hiking team is allowed to move across the heightmap.
+#[derive(Debug, Clone, Copy)]
+pub struct Rules {
+    pub max_ascent: i32,
+    /// Unlimited when `None`.
+    pub max_descent: Option<i32>,
+    pub diagonals: bool,
+    pub cost: Cost,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            max_ascent: 1,
+            max_descent: None,
+            diagonals: false,
+            cost: Cost::Unit,
+        }
+    }
+}
+
+impl Rules {
+    /// Parses `--ascent N`, `--descent N`, `--diagonal` and `--cost unit|climb|steep`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut rules = Rules::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            if flag == "--diagonal" {
+                rules.diagonals = true;
+                continue;
+            }
+
+            let value = args.next().context(format!("{flag} needs a value"))?;
+            match flag.as_str() {
+                "--ascent" => rules.max_ascent = value.parse()?,
+                "--descent" => rules.max_descent = Some(value.parse()?),
+                "--cost" => {
+                    rules.cost = match value.as_str() {
+                        "unit" => Cost::Unit,
+                        "climb" => Cost::Climb,
+                        "steep" => Cost::Steepness,
+                        _ => return Err(anyhow!("unknown cost {value}")),
+                    }
+                }
+                _ => return Err(anyhow!("unknown flag {flag}")),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        const AXES: [(isize, isize); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        const ALL: [(isize, isize); 8] = [
+            (0, 1),
+            (0, -1),
+            (1, 0),
+            (-1, 0),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        if self.diagonals {
+            &ALL
+        } else {
+            &AXES
+        }
+    }
+
+    /// Cost of a move climbing `climb` levels, if it's allowed at all.
+    pub fn cost(&self, climb: i32) -> Option<u32> {
+        let allowed = climb <= self.max_ascent && self.max_descent.is_none_or(|max| -climb <= max);
+        allowed.then(|| self.cost.of(climb))
+    }
+}
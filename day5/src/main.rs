@@ -1,6 +1,10 @@
-use anyhow::{Context, Error, Result};
-use std::{collections::HashMap, str::FromStr};
+use anyhow::{anyhow, Context, Error, Result};
+use std::{collections::HashMap, fmt, str::FromStr};
 
+mod planner;
+use planner::{plan, Crane, MAX_STATES};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct Step {
     count: usize,
     from: usize,
@@ -23,6 +27,13 @@ impl FromStr for Step {
     }
 }
 
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from, self.to)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 struct Map {
     inner: HashMap<usize, Vec<char>>,
 }
@@ -31,21 +42,31 @@ impl FromStr for Map {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.lines().rev();
+        let lines: Vec<&str> = s.lines().collect();
+        let mut iter = lines.iter().enumerate().rev();
 
         let mut inner: HashMap<usize, Vec<char>> = HashMap::new();
-        iter.next().context("couldn't get columns")?;
-        for line in iter {
-            line.chars()
-                .collect::<Vec<_>>()
-                .chunks(4)
-                .map(|s| s[1])
-                .enumerate()
-                .for_each(|(num, c)| {
-                    if c.is_alphabetic() {
-                        inner.entry(num + 1).or_default().push(c);
+        let (_, columns) = iter.next().context("couldn't get columns")?;
+        for num in columns.split_whitespace() {
+            inner.entry(num.parse()?).or_default();
+        }
+        for (row, line) in iter {
+            let chars: Vec<char> = line.trim_end().chars().collect();
+            for (num, chunk) in chars.chunks(4).enumerate() {
+                match chunk {
+                    ['[', c, ']'] | ['[', c, ']', ' '] if c.is_alphabetic() => {
+                        inner.entry(num + 1).or_default().push(*c)
                     }
-                });
+                    [' ', ' ', ' ', ' '] => {}
+                    _ => {
+                        return Err(anyhow!(
+                            "row {}: malformed crate {:?}",
+                            row + 1,
+                            chunk.iter().collect::<String>()
+                        ))
+                    }
+                }
+            }
         }
 
         Ok(Map { inner })
@@ -128,6 +149,11 @@ impl FromStr for World {
 }
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("plan") = args.first().map(String::as_str) {
+        return run_planner(&args[1..]);
+    }
+
     let input = include_str!("../input.txt");
     let mut world: World = input.parse()?;
     world.process_instructions()?;
@@ -141,3 +167,38 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+fn run_planner(args: &[String]) -> Result<()> {
+    let [start, target, rest @ ..] = args else {
+        return Err(anyhow!("usage: plan <start> <target> [9000|9001]"));
+    };
+    let crane = match rest.first().map(String::as_str) {
+        None | Some("9000") => Crane::CrateMover9000,
+        Some("9001") => Crane::CrateMover9001,
+        Some(other) => return Err(anyhow!("unknown crane model {other}")),
+    };
+    let start: Map = std::fs::read_to_string(start)?
+        .trim_end_matches('\n')
+        .parse()?;
+    let target: Map = std::fs::read_to_string(target)?
+        .trim_end_matches('\n')
+        .parse()?;
+
+    for step in plan(&start, &target, crane, MAX_STATES)? {
+        println!("{step}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn map_parses_ragged_lines() -> Result<()> {
+    let map: Map = "[A] [B]  \n[C] [D] [E]\n 1   2   3 ".parse()?;
+    assert_eq!(map.inner[&1], vec!['C', 'A']);
+    assert_eq!(map.inner[&3], vec!['E']);
+
+    let err = "[A] B\n[C] [D]\n 1   2 ".parse::<Map>().err().unwrap();
+    assert_eq!(err.to_string(), "row 1: malformed crate \"B\"");
+    assert!("[A][B]\n 1   2 ".parse::<Map>().is_err());
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{Map, Step};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Crane {
+    /// Moves crates one at a time, so a multi-crate move reverses their order.
+    CrateMover9000,
+    /// Moves several crates at once, keeping their order.
+    CrateMover9001,
+}
+
+type Stacks = Vec<Vec<char>>;
+
+fn stacks(map: &Map) -> Stacks {
+    let mut columns: Vec<_> = map.inner.iter().collect();
+    columns.sort_by_key(|(num, _)| **num);
+    columns.into_iter().map(|(_, col)| col.clone()).collect()
+}
+
+fn apply(stacks: &Stacks, from: usize, to: usize, count: usize, crane: Crane) -> Stacks {
+    let mut next = stacks.clone();
+    let idx = next[from].len() - count;
+    let mut taken = next[from].split_off(idx);
+    if crane == Crane::CrateMover9000 {
+        taken.reverse();
+    }
+    next[to].append(&mut taken);
+    next
+}
+
+fn sorted_crates(stacks: &Stacks) -> Vec<char> {
+    let mut crates: Vec<char> = stacks.iter().flatten().copied().collect();
+    crates.sort_unstable();
+    crates
+}
+
+/// Layouts `plan` looks at before giving up, unless told otherwise.
+pub const MAX_STATES: usize = 2_000_000;
+
+/// A layout the search has reached, pointing back at the one it was reached from.
+struct Node {
+    layout: Stacks,
+    parent: Option<(usize, Step)>,
+    moves: usize,
+    done: bool,
+}
+
+/// Lower bound on the moves still needed. A stack with a wrong crate above the part that
+/// already matches has to be the source of a move, and a stack missing crates has to be the
+/// destination of one. A move has one of each, so it settles at most one of either kind.
+/// Counting misplaced crates instead would overestimate, as one move can carry many.
+fn estimate(current: &Stacks, target: &Stacks) -> usize {
+    let (mut sources, mut destinations) = (0, 0);
+    for (have, want) in current.iter().zip(target) {
+        let kept = have.iter().zip(want).take_while(|(a, b)| a == b).count();
+        sources += (have.len() > kept) as usize;
+        destinations += (want.len() > kept) as usize;
+    }
+    sources.max(destinations)
+}
+
+/// A* search for the fewest `move` steps turning `start` into `target`, giving up once
+/// `max_states` layouts have been seen.
+pub fn plan(start: &Map, target: &Map, crane: Crane, max_states: usize) -> Result<Vec<Step>> {
+    let start = stacks(start);
+    let target = stacks(target);
+
+    if start.len() != target.len() {
+        return Err(anyhow!(
+            "start has {} columns but target has {}",
+            start.len(),
+            target.len()
+        ));
+    }
+    if sorted_crates(&start) != sorted_crates(&target) {
+        return Err(anyhow!("start and target don't hold the same crates"));
+    }
+
+    let mut seen = HashMap::from([(start.clone(), 0)]);
+    let mut queue = BinaryHeap::from([(Reverse(estimate(&start, &target)), 0, 0)]);
+    let mut nodes = vec![Node {
+        layout: start,
+        parent: None,
+        moves: 0,
+        done: false,
+    }];
+
+    // ties go to the deepest layout, which is closest to a full plan
+    while let Some((_, moves, idx)) = queue.pop() {
+        if nodes[idx].done || moves > nodes[idx].moves {
+            continue;
+        }
+        nodes[idx].done = true;
+        let current = nodes[idx].layout.clone();
+
+        if current == target {
+            let mut steps = vec![];
+            let mut cursor = idx;
+            while let Some((prev, step)) = &nodes[cursor].parent {
+                steps.push(step.clone());
+                cursor = *prev;
+            }
+            steps.reverse();
+            return Ok(steps);
+        }
+
+        for from in 0..current.len() {
+            for to in 0..current.len() {
+                if from == to {
+                    continue;
+                }
+                for count in 1..=current[from].len() {
+                    let next = apply(&current, from, to, count, crane);
+                    let step = Step {
+                        count,
+                        from: from + 1,
+                        to: to + 1,
+                    };
+                    let next_idx = match seen.get(&next) {
+                        Some(&known) if nodes[known].done || nodes[known].moves <= moves + 1 => {
+                            continue
+                        }
+                        Some(&known) => known,
+                        None if nodes.len() == max_states => {
+                            return Err(anyhow!("gave up after {max_states} layouts"))
+                        }
+                        None => {
+                            seen.insert(next.clone(), nodes.len());
+                            nodes.push(Node {
+                                layout: next,
+                                parent: None,
+                                moves: 0,
+                                done: false,
+                            });
+                            nodes.len() - 1
+                        }
+                    };
+
+                    let node = &mut nodes[next_idx];
+                    node.parent = Some((idx, step));
+                    node.moves = moves + 1;
+                    let bound = node.moves + estimate(&node.layout, &target);
+                    queue.push((Reverse(bound), node.moves, next_idx));
+                }
+            }
+        }
+    }
+
+    Err(anyhow!("target layout is unreachable"))
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3 ";
+
+#[test]
+fn plan_reaches_target_9000() -> Result<()> {
+    let target = "        [Z]
+        [N]
+        [D]
+[C] [M] [P]
+ 1   2   3 ";
+    let steps = plan(
+        &EXAMPLE.parse()?,
+        &target.parse()?,
+        Crane::CrateMover9000,
+        MAX_STATES,
+    )?;
+    assert!(steps.len() <= 4);
+
+    let mut world = crate::World {
+        map: EXAMPLE.parse()?,
+        steps,
+    };
+    world.process_instructions()?;
+    assert_eq!(world.map, target.parse()?);
+    Ok(())
+}
+
+#[test]
+fn plan_reaches_target_9001() -> Result<()> {
+    let target = "        [D]
+        [N]
+        [Z]
+[M] [C] [P]
+ 1   2   3 ";
+    let steps = plan(
+        &EXAMPLE.parse()?,
+        &target.parse()?,
+        Crane::CrateMover9001,
+        MAX_STATES,
+    )?;
+
+    let mut world = crate::World {
+        map: EXAMPLE.parse()?,
+        steps,
+    };
+    world.process_instructions2()?;
+    assert_eq!(world.map, target.parse()?);
+    Ok(())
+}
+
+#[test]
+fn plan_emits_step_syntax() -> Result<()> {
+    let target = "[D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3 ";
+    let steps = plan(
+        &EXAMPLE.parse()?,
+        &target.parse()?,
+        Crane::CrateMover9000,
+        MAX_STATES,
+    )?;
+    let lines: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+    assert_eq!(lines, vec!["move 1 from 2 to 1"]);
+    assert_eq!(lines[0].parse::<Step>()?, steps[0]);
+    Ok(())
+}
+
+#[test]
+fn plan_rejects_different_crates() -> Result<()> {
+    let target = "[A] [C]
+[Z] [M] [P]
+ 1   2   3 ";
+    assert!(plan(
+        &EXAMPLE.parse()?,
+        &target.parse()?,
+        Crane::CrateMover9000,
+        MAX_STATES
+    )
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn plan_handles_the_full_input() -> Result<()> {
+    let (drawing, _) = include_str!("../input.txt").split_once("\n\n").unwrap();
+    let start: Map = drawing.parse()?;
+    let mut world = crate::World {
+        map: drawing.parse()?,
+        steps: vec![
+            "move 3 from 2 to 6".parse()?,
+            "move 2 from 1 to 3".parse()?,
+            "move 4 from 6 to 9".parse()?,
+        ],
+    };
+    world.process_instructions2()?;
+
+    let steps = plan(&start, &world.map, Crane::CrateMover9001, MAX_STATES)?;
+    assert!(steps.len() <= 3);
+
+    let err = plan(&start, &world.map, Crane::CrateMover9001, 100).unwrap_err();
+    assert_eq!(err.to_string(), "gave up after 100 layouts");
+    Ok(())
+}
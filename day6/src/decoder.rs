@@ -0,0 +1,162 @@
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read},
+};
+
+use anyhow::Result;
+
+use crate::MarkerWindow;
+
+pub const START_OF_PACKET: usize = 4;
+pub const START_OF_MESSAGE: usize = 14;
+
+/// The bytes between one marker and the next marker of the same length (or the end of
+/// the stream).
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub window_length: usize,
+    /// How many bytes had been read when the opening marker completed.
+    pub marker: usize,
+    pub payload: Vec<u8>,
+}
+
+struct Channel {
+    window: MarkerWindow<u8>,
+    marker: Option<usize>,
+    payload: Vec<u8>,
+}
+
+/// Reads a datastream once, looking for markers of every requested length at the same
+/// time, and yields frames in the order they are closed.
+pub struct Decoder<R> {
+    reader: R,
+    channels: Vec<Channel>,
+    ready: VecDeque<Frame>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R, window_lengths: &[usize]) -> Self {
+        let channels = window_lengths
+            .iter()
+            .map(|&window_length| Channel {
+                window: MarkerWindow::new(window_length),
+                marker: None,
+                payload: vec![],
+            })
+            .collect();
+
+        Self {
+            reader,
+            channels,
+            ready: VecDeque::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.position += 1;
+
+        for channel in &mut self.channels {
+            if channel.marker.is_some() {
+                channel.payload.push(byte);
+            }
+            if !channel.window.push(byte) {
+                continue;
+            }
+
+            let window_length = channel.window.window_length;
+            if let Some(marker) = channel.marker {
+                // the new marker isn't part of the previous frame
+                let mut payload = std::mem::take(&mut channel.payload);
+                payload.truncate(payload.len() - window_length);
+                self.ready.push_back(Frame {
+                    window_length,
+                    marker,
+                    payload,
+                });
+            }
+            channel.marker = Some(self.position);
+            channel.window.reset();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+
+        for channel in &mut self.channels {
+            if let Some(marker) = channel.marker.take() {
+                self.ready.push_back(Frame {
+                    window_length: channel.window.window_length,
+                    marker,
+                    payload: std::mem::take(&mut channel.payload),
+                });
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [0; 4096];
+
+        while self.ready.is_empty() && !self.finished {
+            match self.reader.read(&mut buf) {
+                Ok(0) => self.finish(),
+                Ok(len) => buf[..len].iter().for_each(|byte| self.push(*byte)),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+#[test]
+fn decoder_finds_same_markers_as_find_marker() -> Result<()> {
+    let s = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+    let markers = Decoder::new(s.as_bytes(), &[START_OF_PACKET, START_OF_MESSAGE])
+        .map(|frame| frame.map(|frame| (frame.window_length, frame.marker)))
+        .collect::<Result<Vec<_>>>()?;
+
+    assert!(markers.contains(&(START_OF_PACKET, crate::find_marker(s, 4)?.unwrap())));
+    assert!(markers.contains(&(START_OF_MESSAGE, crate::find_marker(s, 14)?.unwrap())));
+    Ok(())
+}
+
+#[test]
+fn decoder_frames_payloads() -> Result<()> {
+    let frames = Decoder::new("abcdXXXabcYY".as_bytes(), &[4]).collect::<Result<Vec<_>>>()?;
+
+    assert_eq!(
+        frames,
+        vec![
+            Frame {
+                window_length: 4,
+                marker: 4,
+                payload: b"XX".to_vec(),
+            },
+            Frame {
+                window_length: 4,
+                marker: 10,
+                payload: b"YY".to_vec(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn decoder_ignores_streams_without_markers() -> Result<()> {
+    let frames = Decoder::new("aaaaaaaa".as_bytes(), &[4, 14]).collect::<Result<Vec<_>>>()?;
+    assert!(frames.is_empty());
+    Ok(())
+}
@@ -1,33 +1,166 @@
-const OFFSET: u8 = b'a';
-fn main() {
-    let input = include_str!("../input.txt");
-    let part1 = find_marker(input, 4).expect("couldn't find 4 char unique sequence");
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+mod decoder;
+use decoder::{Decoder, START_OF_MESSAGE, START_OF_PACKET};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("decode") = args.first().map(String::as_str) {
+        return decode(args.get(1));
+    }
+
+    let input = include_str!("../input.txt").trim_end();
+    let part1 =
+        find_marker(input, START_OF_PACKET)?.context("couldn't find 4 char unique sequence")?;
     println!("part1: {part1}");
 
-    let part2 = find_marker(input, 14).expect("couldn't find 4 char unique sequence");
+    let part2 =
+        find_marker(input, START_OF_MESSAGE)?.context("couldn't find 14 char unique sequence")?;
     println!("part2: {part2}");
+
+    Ok(())
+}
+
+/// Prints every frame in a captured stream, read from `path` or stdin.
+fn decode(path: Option<&String>) -> Result<()> {
+    let reader: Box<dyn std::io::Read> = match path {
+        Some(path) => Box::new(std::fs::File::open(path)?),
+        None => Box::new(std::io::stdin().lock()),
+    };
+
+    for frame in Decoder::new(reader, &[START_OF_PACKET, START_OF_MESSAGE]) {
+        let frame = frame?;
+        let kind = match frame.window_length {
+            START_OF_PACKET => "packet".to_string(),
+            START_OF_MESSAGE => "message".to_string(),
+            other => format!("{other} symbol"),
+        };
+        println!(
+            "{kind} after marker at {}: {:?}",
+            frame.marker,
+            String::from_utf8_lossy(&frame.payload)
+        );
+    }
+
+    Ok(())
+}
+
+/// Finds the marker in a datastream made up of `a..=z`, erroring on any other symbol.
+fn find_marker(s: &str, window_length: usize) -> Result<Option<usize>> {
+    if let Some((idx, c)) = s.char_indices().find(|(_, c)| !c.is_ascii_lowercase()) {
+        return Err(anyhow!("unexpected symbol {c:?} at byte {idx}"));
+    }
+
+    Ok(find_marker_by(s.bytes(), window_length))
 }
 
-fn find_marker(s: &str, window_length: usize) -> Option<usize> {
-    let c = s.chars().collect::<Vec<_>>();
-    c.windows(window_length)
+/// Returns how many symbols have been read once the last `window_length` of them are all
+/// different. Works for any symbol, e.g. `s.bytes()` or `s.chars()`, in a single pass.
+fn find_marker_by<T: Copy + Eq + Hash>(
+    symbols: impl IntoIterator<Item = T>,
+    window_length: usize,
+) -> Option<usize> {
+    if window_length == 0 {
+        return Some(0);
+    }
+
+    let mut window = MarkerWindow::new(window_length);
+    symbols
+        .into_iter()
         .enumerate()
-        .find_map(|(idx, window)| {
-            let mut alphabet = [false; 26];
-            for c in window.iter() {
-                let letter = (*c as u8 - OFFSET) as usize;
-                if alphabet[letter] {
-                    return None;
+        .find_map(|(idx, symbol)| window.push(symbol).then_some(idx + 1))
+}
+
+/// Sliding window keeping symbol counts up to date as symbols enter and leave it.
+struct MarkerWindow<T> {
+    window_length: usize,
+    window: VecDeque<T>,
+    counts: HashMap<T, usize>,
+    // how many symbols occur more than once in the window
+    repeated: usize,
+}
+
+impl<T: Copy + Eq + Hash> MarkerWindow<T> {
+    fn new(window_length: usize) -> Self {
+        Self {
+            window_length,
+            window: VecDeque::with_capacity(window_length),
+            counts: HashMap::new(),
+            repeated: 0,
+        }
+    }
+
+    /// Adds a symbol, returning whether the window is now full of different symbols.
+    fn push(&mut self, symbol: T) -> bool {
+        if self.window_length == 0 {
+            return true;
+        }
+
+        if self.window.len() == self.window_length {
+            if let Some(oldest) = self.window.pop_front() {
+                let count = self
+                    .counts
+                    .get_mut(&oldest)
+                    .expect("oldest symbol is counted");
+                *count -= 1;
+                match *count {
+                    0 => {
+                        self.counts.remove(&oldest);
+                    }
+                    1 => self.repeated -= 1,
+                    _ => {}
                 }
-                alphabet[letter] = true;
             }
-            Some(idx)
-        })
-        .map(|idx| idx + window_length)
+        }
+
+        let count = self.counts.entry(symbol).or_default();
+        *count += 1;
+        if *count == 2 {
+            self.repeated += 1;
+        }
+        self.window.push_back(symbol);
+
+        self.window.len() == self.window_length && self.repeated == 0
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.counts.clear();
+        self.repeated = 0;
+    }
 }
 
 #[test]
-fn find_marker_works() {
+fn find_marker_works() -> Result<()> {
     let s = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-    assert_eq!(find_marker(s, 4), Some(5));
+    assert_eq!(find_marker(s, 4)?, Some(5));
+    Ok(())
+}
+
+#[test]
+fn find_marker_message_works() -> Result<()> {
+    assert_eq!(find_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14)?, Some(19));
+    assert_eq!(
+        find_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14)?,
+        Some(26)
+    );
+    assert_eq!(find_marker("aaaa", 4)?, None);
+    Ok(())
+}
+
+#[test]
+fn find_marker_errors_on_unexpected_symbols() {
+    let err = find_marker("abcD", 4).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected symbol 'D' at byte 3");
+}
+
+#[test]
+fn find_marker_by_works_on_any_symbol() {
+    assert_eq!(find_marker_by("AAé\u{1F980}é!#".chars(), 3), Some(4));
+    assert_eq!(find_marker_by([0u8, 0, 255, 0, 7, 128], 3), Some(5));
 }
@@ -0,0 +1,119 @@
+use std::{
+    fmt::Write,
+    fs::{self, File},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    filesystem::{FileSystem, ROOT},
+    query::{walk, Entry},
+};
+
+/// Recreates the tree under `target`. Files are only given a length, so on most filesystems
+/// they are sparse and take next to no space. Existing files are never overwritten.
+pub fn materialise(fs: &FileSystem, target: &Path) -> Result<()> {
+    fs::create_dir_all(target)?;
+
+    for (_, entry) in walk(fs, ROOT) {
+        match entry {
+            Entry::Dir(id) => {
+                let path = target.join(fs.path(id).trim_start_matches('/'));
+                fs::create_dir_all(&path).context(format!("can't create {}", path.display()))?;
+            }
+            Entry::File { parent, name, size } => {
+                let path = target
+                    .join(fs.path(parent).trim_start_matches('/'))
+                    .join(name);
+                File::create_new(&path)
+                    .and_then(|file| file.set_len(size as u64))
+                    .context(format!("can't create {}", path.display()))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks a real directory and writes the `$ cd`/`$ ls` session that would explore it.
+/// Symlinks and anything else that isn't a plain file or directory are left out.
+pub fn transcript(root: &Path) -> Result<String> {
+    let mut out = String::from("$ cd /\n");
+    explore(root, &mut out)?;
+    Ok(out)
+}
+
+fn explore(dir: &Path, out: &mut String) -> Result<()> {
+    let mut dirs = vec![];
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).context(format!("can't read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| anyhow!("{name:?} isn't valid UTF-8"))?;
+        if name.contains('\n') {
+            return Err(anyhow!("{name:?} can't be written to a transcript"));
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            dirs.push(name);
+        } else if file_type.is_file() {
+            files.push((name, entry.metadata()?.len()));
+        }
+    }
+    dirs.sort();
+    files.sort();
+
+    out.push_str("$ ls\n");
+    for name in &dirs {
+        writeln!(out, "dir {name}")?;
+    }
+    for (name, size) in &files {
+        writeln!(out, "{size} {name}")?;
+    }
+
+    for name in &dirs {
+        writeln!(out, "$ cd {name}")?;
+        explore(&dir.join(name), out)?;
+        out.push_str("$ cd ..\n");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn round_trips_through_disk() -> Result<()> {
+    let fs = crate::filesystem::example();
+    let target = std::env::temp_dir().join(format!("day7-round-trip-{}", std::process::id()));
+
+    materialise(&fs, &target)?;
+    let written = transcript(&target);
+    let again = materialise(&fs, &target);
+    fs::remove_dir_all(&target)?;
+
+    let parsed: FileSystem = written?.parse()?;
+    assert_eq!(
+        crate::query::tree(&parsed, ROOT),
+        crate::query::tree(&fs, ROOT)
+    );
+    assert!(again.is_err(), "existing files shouldn't be overwritten");
+    Ok(())
+}
+
+#[test]
+fn transcript_handles_spaces_in_names() -> Result<()> {
+    let target = std::env::temp_dir().join(format!("day7-spaces-{}", std::process::id()));
+    fs::create_dir_all(target.join("my dir"))?;
+    File::create(target.join("my dir").join("a file.txt"))?.set_len(12)?;
+
+    let written = transcript(&target);
+    fs::remove_dir_all(&target)?;
+
+    let parsed: FileSystem = written?.parse()?;
+    let id = parsed.lookup("/my dir").context("dir is missing")?;
+    assert_eq!(parsed.dir(id).files["a file.txt"], 12);
+    Ok(())
+}
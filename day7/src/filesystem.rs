@@ -0,0 +1,337 @@
+use anyhow::{anyhow, Context, Error, Result};
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+pub type DirId = usize;
+
+pub const ROOT: DirId = 0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DirSize {
+    /// Size of everything below the directory.
+    pub total: usize,
+    /// Size of the files directly inside the directory.
+    pub direct: usize,
+    pub total_files: usize,
+    pub files: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Dir {
+    pub name: String,
+    pub parent: Option<DirId>,
+    pub dirs: BTreeMap<String, DirId>,
+    /// File names and their sizes.
+    pub files: BTreeMap<String, usize>,
+    listed: bool,
+}
+
+/// Directory tree rebuilt from a terminal transcript. Directories live in an arena and refer to
+/// each other by index, the root being [`ROOT`].
+pub struct FileSystem {
+    dirs: Vec<Dir>,
+}
+
+/// Output of the `ls` currently being read.
+struct Listing {
+    seen: HashSet<String>,
+    relisting: bool,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self {
+            dirs: vec![Dir {
+                name: "/".into(),
+                ..Default::default()
+            }],
+        }
+    }
+}
+
+impl FileSystem {
+    pub fn dir(&self, id: DirId) -> &Dir {
+        &self.dirs[id]
+    }
+
+    pub fn path(&self, id: DirId) -> String {
+        let mut names = vec![];
+        let mut cursor = id;
+        while let Some(parent) = self.dirs[cursor].parent {
+            names.push(self.dirs[cursor].name.as_str());
+            cursor = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    fn add_dir(&mut self, parent: DirId, name: &str) -> DirId {
+        let id = self.dirs.len();
+        self.dirs.push(Dir {
+            name: name.into(),
+            parent: Some(parent),
+            ..Default::default()
+        });
+        self.dirs[parent].dirs.insert(name.into(), id);
+        id
+    }
+
+    fn cd(&mut self, cwd: DirId, name: &str) -> Result<DirId> {
+        check_name(name)?;
+        let dir = &self.dirs[cwd];
+        if let Some(id) = dir.dirs.get(name) {
+            return Ok(*id);
+        }
+        if dir.files.contains_key(name) {
+            return Err(anyhow!("{name} is a file"));
+        }
+        if dir.listed {
+            return Err(anyhow!("no directory {name} in {}", self.path(cwd)));
+        }
+
+        // entering a directory before its parent was listed
+        Ok(self.add_dir(cwd, name))
+    }
+
+    fn start_listing(&mut self, cwd: DirId) -> Listing {
+        let relisting = std::mem::replace(&mut self.dirs[cwd].listed, true);
+        Listing {
+            seen: HashSet::new(),
+            relisting,
+        }
+    }
+
+    fn record(&mut self, cwd: DirId, listing: &mut Listing, line: &str) -> Result<()> {
+        let (kind, name) = line
+            .split_once(' ')
+            .context("can't get entry kind and name")?;
+        check_name(name)?;
+        if !listing.seen.insert(name.into()) {
+            return Err(anyhow!("{name} listed twice"));
+        }
+
+        let dir = &mut self.dirs[cwd];
+        let exists = if kind == "dir" {
+            if dir.files.contains_key(name) {
+                return Err(anyhow!("{name} was listed as a file before"));
+            }
+            dir.dirs.contains_key(name)
+        } else {
+            let size: usize = kind.parse().context(format!("can't parse size {kind}"))?;
+            if dir.dirs.contains_key(name) {
+                return Err(anyhow!("{name} was listed as a directory before"));
+            }
+            match dir.files.get(name) {
+                Some(&old) if old != size => {
+                    return Err(anyhow!("{name} was listed with size {old}, now {size}"))
+                }
+                Some(_) => true,
+                None => false,
+            }
+        };
+
+        if exists {
+            return Ok(());
+        }
+        if listing.relisting {
+            return Err(anyhow!("{name} is missing from the earlier listing"));
+        }
+        if kind == "dir" {
+            self.add_dir(cwd, name);
+        } else {
+            self.dirs[cwd].files.insert(name.into(), kind.parse()?);
+        }
+        Ok(())
+    }
+
+    fn finish_listing(&self, cwd: DirId, listing: Listing) -> Result<()> {
+        let dir = &self.dirs[cwd];
+        let missing = dir
+            .dirs
+            .keys()
+            .chain(dir.files.keys())
+            .find(|name| !listing.seen.contains(*name));
+
+        match missing {
+            Some(name) => Err(anyhow!("listing of {} is missing {name}", self.path(cwd))),
+            None => Ok(()),
+        }
+    }
+
+    /// Sizes of every directory, indexed by [`DirId`]. Children are always added after their
+    /// parent, so walking the arena backwards visits each directory after all of its
+    /// descendants and one pass is enough.
+    pub fn sizes(&self) -> Vec<DirSize> {
+        let mut sizes: Vec<DirSize> = self
+            .dirs
+            .iter()
+            .map(|dir| {
+                let direct = dir.files.values().sum();
+                DirSize {
+                    total: direct,
+                    direct,
+                    total_files: dir.files.len(),
+                    files: dir.files.len(),
+                }
+            })
+            .collect();
+
+        for (id, dir) in self.dirs.iter().enumerate().rev() {
+            if let Some(parent) = dir.parent {
+                let DirSize {
+                    total, total_files, ..
+                } = sizes[id];
+                sizes[parent].total += total;
+                sizes[parent].total_files += total_files;
+            }
+        }
+
+        sizes
+    }
+
+    /// Finds a directory by its absolute path, e.g. `/a/e`.
+    pub fn lookup(&self, path: &str) -> Option<DirId> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(ROOT, |id, name| self.dirs[id].dirs.get(name).copied())
+    }
+}
+
+/// Entries have to be plain names, so that joining them onto a real directory can't leave it.
+fn check_name(name: &str) -> Result<()> {
+    if matches!(name, "" | "." | "..") || name.contains('/') {
+        return Err(anyhow!("{name:?} isn't a valid entry name"));
+    }
+    Ok(())
+}
+
+impl FromStr for FileSystem {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fs = FileSystem::default();
+        let mut cwd = ROOT;
+        let mut listing: Option<Listing> = None;
+
+        for (idx, line) in s.lines().enumerate() {
+            let mut read_line = || -> Result<()> {
+                let Some(command) = line.strip_prefix("$ ") else {
+                    let current = listing.as_mut().context("output outside of ls")?;
+                    return fs.record(cwd, current, line);
+                };
+
+                if let Some(done) = listing.take() {
+                    fs.finish_listing(cwd, done)?;
+                }
+
+                // names can contain spaces, so only split off the command
+                match command.split_once(' ').unwrap_or((command, "")) {
+                    ("ls", "") => listing = Some(fs.start_listing(cwd)),
+                    ("cd", "/") => cwd = ROOT,
+                    ("cd", "..") => cwd = fs.dirs[cwd].parent.unwrap_or(ROOT),
+                    ("cd", name) if !name.is_empty() => cwd = fs.cd(cwd, name)?,
+                    _ => return Err(anyhow!("unknown command")),
+                }
+                Ok(())
+            };
+
+            read_line().with_context(|| format!("line {}: {line}", idx + 1))?;
+        }
+
+        if let Some(done) = listing {
+            fs.finish_listing(cwd, done)?;
+        }
+
+        Ok(fs)
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = include_str!("../test/fixtures/example.txt");
+
+/// The puzzle's example, shared by every module's tests.
+#[cfg(test)]
+pub fn example() -> FileSystem {
+    EXAMPLE.parse().expect("example parses")
+}
+
+#[test]
+fn parses_example() -> Result<()> {
+    let fs = example();
+    let sizes = fs.sizes();
+    let total = |path| fs.lookup(path).map(|id| sizes[id].total);
+    assert_eq!(total("/a/e"), Some(584));
+    assert_eq!(total("/a"), Some(94853));
+    assert_eq!(total("/d"), Some(24933642));
+    assert_eq!(total("/"), Some(48381165));
+    assert_eq!(total("/b.txt"), None);
+    Ok(())
+}
+
+#[test]
+fn sizes_count_direct_and_total() -> Result<()> {
+    let fs = example();
+    let sizes = fs.sizes();
+    assert_eq!(
+        sizes[ROOT],
+        DirSize {
+            total: 48381165,
+            direct: 14848514 + 8504156,
+            total_files: 10,
+            files: 2,
+        }
+    );
+    assert_eq!(sizes[fs.lookup("/a").unwrap()].files, 3);
+    assert_eq!(sizes[fs.lookup("/a").unwrap()].total_files, 4);
+    Ok(())
+}
+
+#[test]
+fn revisiting_and_relisting_is_idempotent() -> Result<()> {
+    let transcript = format!("{EXAMPLE}$ cd /\n$ cd ..\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d\n$ cd a\n$ cd e\n$ ls\n584 i");
+    let fs: FileSystem = transcript.parse()?;
+    assert_eq!(fs.sizes()[ROOT].total, 48381165);
+    assert_eq!(fs.sizes().len(), 4);
+    Ok(())
+}
+
+#[test]
+fn conflicting_listings_error() {
+    let transcript = format!("{EXAMPLE}$ cd /\n$ ls\ndir a\n1 b.txt");
+    let err = transcript.parse::<FileSystem>().err().unwrap();
+    assert_eq!(
+        format!("{err:#}"),
+        "line 27: 1 b.txt: b.txt was listed with size 14848514, now 1"
+    );
+
+    let transcript = format!("{EXAMPLE}$ cd /\n$ ls\ndir a\n$ cd a");
+    let err = transcript.parse::<FileSystem>().err().unwrap();
+    assert_eq!(
+        format!("{err:#}"),
+        "line 27: $ cd a: listing of / is missing d"
+    );
+
+    let err = "$ cd /\n$ ls\n1 a\n$ cd a"
+        .parse::<FileSystem>()
+        .err()
+        .unwrap();
+    assert_eq!(format!("{err:#}"), "line 4: $ cd a: a is a file");
+}
+
+#[test]
+fn rejects_names_that_leave_the_directory() {
+    let error = |transcript: &str| format!("{:#}", transcript.parse::<FileSystem>().err().unwrap());
+    assert_eq!(
+        error("$ cd /\n$ ls\n12 ../escaped.txt"),
+        "line 3: 12 ../escaped.txt: \"../escaped.txt\" isn't a valid entry name"
+    );
+    assert_eq!(
+        error("$ cd /\n$ ls\n12 /etc/foo"),
+        "line 3: 12 /etc/foo: \"/etc/foo\" isn't a valid entry name"
+    );
+    assert!("$ cd /\n$ ls\ndir ..".parse::<FileSystem>().is_err());
+    assert!("$ cd /\n$ ls\n1 .".parse::<FileSystem>().is_err());
+    assert!("$ cd /\n$ cd a/b".parse::<FileSystem>().is_err());
+}
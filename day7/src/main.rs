@@ -1,104 +1,103 @@
-#![feature(iter_advance_by)]
+use anyhow::{anyhow, Context, Result};
 
-use anyhow::{anyhow, Context, Error, Result};
-use std::{collections::{HashMap, HashSet}, str::FromStr, ops::ControlFlow};
+mod bridge;
+use bridge::{materialise, transcript};
 
-enum Node {
-    Dir { name: String },
-    File { name: String, size: usize },
-}
-
-struct FileSystem {
-    inner: std::collections::HashMap<String, Vec<Node>>,
-}
-
-impl FromStr for FileSystem {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut inner: HashMap<String, Vec<Node>> = HashMap::new();
-        let mut seen = HashSet::new();
-
-        let mut current_key = Vec::new();
-        for line in s.lines() {
-            if line.starts_with("$ cd ..") {
-                current_key.pop().context("tried to pop key but nothing left")?;
-                continue
-            } else if line.starts_with("$ ls") {
-                continue
-            }
+mod filesystem;
+use filesystem::{DirId, FileSystem, ROOT};
 
-            if let Some(key) = line.strip_prefix("$ cd ") {
-                current_key.push(key);
+mod planner;
+use planner::{plan, Disk};
 
-                if !seen.insert(current_key.join("/")) {
-                    return Err(anyhow!(format!("tried to insert duplicate key {key}")));
-                }
-            } else if let Some(dir_name) = line.strip_prefix("dir ") {
-                inner
-                    .entry(current_key.join("/"))
-                    .or_default()
-                    .push(Node::Dir {
-                        name: current_key.join("/") + "/" + dir_name,
-                    })
-            } else {
-                let (size, name) = line
-                    .split_once(' ')
-                    .context("can't get file size and name")?;
+mod query;
+use query::{du, find, tree, Query};
 
-                inner
-                    .entry(current_key.join("/"))
-                    .or_default()
-                    .push(Node::File {
-                        name: name.into(),
-                        size: size.parse().context(format!("can't parse size {size} {line}"))?,
-                    })
+fn main() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let fs: FileSystem = match args.first().map(String::as_str) {
+        Some("-t") => {
+            let path = args.get(1).context("-t needs a transcript path")?.clone();
+            args.drain(..2);
+            std::fs::read_to_string(path)?.parse()?
+        }
+        _ => include_str!("../input.txt").parse()?,
+    };
+
+    let Some(command) = args.first() else {
+        return answers(&fs);
+    };
+    // queries take an optional directory path straight after the command
+    let in_dir = || match args.get(1) {
+        Some(path) if path.starts_with('/') => Ok((lookup(&fs, path)?, &args[2..])),
+        _ => Ok::<_, anyhow::Error>((ROOT, &args[1..])),
+    };
+
+    match command.as_str() {
+        "tree" => print!("{}", tree(&fs, in_dir()?.0)),
+        "du" => du(&fs, in_dir()?.0)
+            .into_iter()
+            .for_each(|(size, path)| println!("{size}\t{path}")),
+        "find" => {
+            let (root, rest) = in_dir()?;
+            find(&fs, root, &Query::parse(rest)?)
+                .into_iter()
+                .for_each(|path| println!("{path}"))
+        }
+        "plan" => {
+            let plan = plan(&fs, Disk::parse(&args[1..])?)?;
+            let sizes = fs.sizes();
+            for id in &plan.dirs {
+                println!("{}\t{}", sizes[*id].total, fs.path(*id));
             }
+            println!("needed {}, freed {}", plan.need, plan.freed);
+        }
+        "materialise" => {
+            let target = args
+                .get(1)
+                .context("materialise needs a target directory")?;
+            materialise(&fs, target.as_ref())?;
+        }
+        "transcript" => {
+            let source = args.get(1).context("transcript needs a source directory")?;
+            print!("{}", transcript(source.as_ref())?);
+        }
+        _ => {
+            return Err(anyhow!(
+                "unknown command {command}, expected tree, du, find, plan, \
+                 materialise or transcript"
+            ))
         }
-        Ok(Self { inner })
     }
-}
-
-impl FileSystem {
-    fn dir_size(&self) -> HashMap<String, usize> {
-        let mut dir_size = HashMap::new();
-
-        loop {
-            let before = dir_size.len();
-            for (dir, children) in &self.inner {
-                if dir_size.contains_key(dir) { continue }
-
-                let ControlFlow::Continue(size) = children.iter().try_fold(0,  |memo, item| {
-                    match item {
-                        Node::Dir { name } => match dir_size.get(name) {
-                            Some(val) => ControlFlow::Continue(val + memo),
-                            None => ControlFlow::Break(()),
-                        },
-                        Node::File { size, .. } => ControlFlow::Continue(size + memo),
-                    }
-                }) else { continue };
-                dir_size.insert(dir.to_string(), size);
-            }
 
-            if before == dir_size.len() { break }
-        }
+    Ok(())
+}
 
-        dir_size
-    }
+fn lookup(fs: &FileSystem, path: &str) -> Result<DirId> {
+    fs.lookup(path).context(format!("no directory {path}"))
 }
 
-fn main() -> Result<()> {
-    let input = include_str!("../input.txt");
-    let fs: FileSystem = input.parse()?;
-    let dir_sizes = fs.dir_size();
-    let part1: usize = dir_sizes.iter().filter_map(|(_, size)| (size <= &100_000).then_some(size)).sum();
+fn answers(fs: &FileSystem) -> Result<()> {
+    let dir_sizes = fs.sizes();
+    let part1: usize = dir_sizes
+        .iter()
+        .map(|size| size.total)
+        .filter(|size| *size <= 100_000)
+        .sum();
     println!("part1: {part1}");
 
-    let free: usize = 70_000_000 - *dir_sizes.get("/").context("can't get outer size")?;
-    let need = 30_000_000usize.saturating_sub(free);
-    let mut big_enough: Vec<(&String, &usize)> = dir_sizes.iter().filter(|(_, size)| (size >= &&need)).collect();
-    big_enough.sort_by(|(_, val1), (_, val2)| val1.cmp(val2));
-    let part2 = big_enough.first().context("couldn't get anything big enough")?.1;
+    let disk = Disk::default();
+    let used = dir_sizes[ROOT].total;
+    let free = disk.capacity.checked_sub(used).context(format!(
+        "{used} used is more than the capacity {}",
+        disk.capacity
+    ))?;
+    let need = disk.target_free.saturating_sub(free);
+    let part2 = dir_sizes
+        .iter()
+        .map(|size| size.total)
+        .filter(|size| *size >= need)
+        .min()
+        .context("couldn't get anything big enough")?;
     println!("part2: {part2}");
 
     Ok(())
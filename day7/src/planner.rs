@@ -0,0 +1,380 @@
+use anyhow::{anyhow, Context, Result};
+
+use crate::filesystem::{DirId, DirSize, FileSystem, ROOT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Disk {
+    pub capacity: usize,
+    /// Free space needed once the deletions are done.
+    pub target_free: usize,
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            capacity: 70_000_000,
+            target_free: 30_000_000,
+        }
+    }
+}
+
+impl Disk {
+    /// Parses `--capacity N` and `--free N`, falling back to the puzzle's sizes.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut disk = Disk::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            let value = args.next().context(format!("{flag} needs a value"))?;
+            match flag.as_str() {
+                "--capacity" => disk.capacity = value.parse()?,
+                "--free" => disk.target_free = value.parse()?,
+                _ => return Err(anyhow!("unknown flag {flag}")),
+            }
+        }
+
+        Ok(disk)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Plan {
+    /// How much has to be freed to reach the target.
+    pub need: usize,
+    pub dirs: Vec<DirId>,
+    pub freed: usize,
+}
+
+/// Fixed size set of amounts, one bit each.
+struct Bits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bits {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        bit < self.len && self.words[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    fn insert(&mut self, bit: usize) {
+        if bit < self.len {
+            self.words[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn union(&mut self, other: &Bits) {
+        self.words
+            .iter_mut()
+            .zip(&other.words)
+            .for_each(|(word, other)| *word |= other);
+    }
+
+    /// Adds every amount of `other` increased by `shift`, dropping those that don't fit.
+    fn union_shifted(&mut self, other: &Bits, shift: usize) {
+        let (words, bits) = (shift / 64, shift % 64);
+        for idx in (words..self.words.len()).rev() {
+            let low = other.words[idx - words];
+            let carry = match (bits, idx.checked_sub(words + 1)) {
+                (0, _) | (_, None) => 0,
+                (_, Some(from)) => other.words[from] >> (64 - bits),
+            };
+            self.words[idx] |= (low << bits) | carry;
+        }
+        if !self.len.is_multiple_of(64) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << (self.len % 64)) - 1;
+            }
+        }
+    }
+
+    fn first_from(&self, start: usize) -> Option<usize> {
+        let mut idx = start / 64;
+        let mut word = *self.words.get(idx)? & (u64::MAX << (start % 64));
+        while word == 0 {
+            idx += 1;
+            word = *self.words.get(idx)?;
+        }
+        Some(idx * 64 + word.trailing_zeros() as usize)
+    }
+}
+
+/// Directories in pre-order, so every subtree is a contiguous range of positions.
+struct Layout {
+    order: Vec<DirId>,
+    total: Vec<usize>,
+    /// Position just past the subtree starting at each position.
+    skip: Vec<usize>,
+}
+
+impl Layout {
+    fn new(fs: &FileSystem, sizes: &[DirSize]) -> Self {
+        let mut order = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(fs.dir(id).dirs.values().rev());
+        }
+        let total = order.iter().map(|id| sizes[*id].total).collect();
+
+        let mut skip = vec![0; order.len()];
+        for pos in (0..order.len()).rev() {
+            let children = fs.dir(order[pos]).dirs.len();
+            skip[pos] = (0..children).fold(pos + 1, |next, _| skip[next]);
+        }
+
+        Self { order, total, skip }
+    }
+
+    /// Walks positions `0..end`, handing `visit` the amounts below `limit` that can be freed
+    /// by deleting directories wholly before each position. Deleting the directory at a
+    /// position makes its size available again from the end of its subtree on.
+    fn sweep(&self, end: usize, limit: usize, mut visit: impl FnMut(usize, &Bits) -> bool) {
+        let mut available = Bits::new(limit);
+        available.insert(0);
+        let mut pending: Vec<Option<Bits>> = (0..=end).map(|_| None).collect();
+
+        for pos in 0..end {
+            if let Some(bits) = pending[pos].take() {
+                available.union(&bits);
+            }
+            if !visit(pos, &available) {
+                return;
+            }
+
+            let skip = self.skip[pos].min(end);
+            if self.total[pos] < limit {
+                pending[skip]
+                    .get_or_insert_with(|| Bits::new(limit))
+                    .union_shifted(&available, self.total[pos]);
+            }
+        }
+    }
+}
+
+/// Smallest total deletion of directories, none inside another, that frees enough space.
+///
+/// This is a subset sum over the directories, tracking which amounts below `need` can be
+/// freed as the pre-order is walked. Deleting a directory rules out everything inside it, so
+/// its amounts only become usable once its subtree has been passed.
+pub fn plan(fs: &FileSystem, disk: Disk) -> Result<Plan> {
+    let sizes = fs.sizes();
+    let used = sizes[ROOT].total;
+    let free = disk.capacity.checked_sub(used).context(format!(
+        "{used} used is more than the capacity {}",
+        disk.capacity
+    ))?;
+    let need = disk.target_free.saturating_sub(free);
+    if need > used {
+        return Err(anyhow!("can't free {need}, only {used} is used"));
+    }
+
+    let layout = Layout::new(fs, &sizes);
+    let mut best = usize::MAX;
+    layout.sweep(layout.order.len(), need, |pos, available| {
+        let size = layout.total[pos];
+        if let Some(freed) = available.first_from(need.saturating_sub(size)) {
+            best = best.min(freed + size);
+        }
+        best != need
+    });
+    if need == 0 {
+        best = 0;
+    }
+
+    // work backwards, each time finding a directory before the last one chosen that leaves
+    // an amount which could be freed without it
+    let mut dirs = vec![];
+    let mut remaining = best;
+    let mut end = layout.order.len();
+    while remaining > 0 {
+        let mut found = None;
+        layout.sweep(end, need, |pos, available| {
+            let size = layout.total[pos];
+            let fits = layout.skip[pos] <= end
+                && size <= remaining
+                && available.contains(remaining - size);
+            if fits {
+                found = Some(pos);
+            }
+            !fits
+        });
+
+        let pos = found.context("couldn't retrace the plan")?;
+        dirs.push(layout.order[pos]);
+        remaining -= layout.total[pos];
+        end = pos;
+    }
+    dirs.reverse();
+
+    Ok(Plan {
+        need,
+        dirs,
+        freed: best,
+    })
+}
+
+#[cfg(test)]
+fn paths(fs: &FileSystem, plan: &Plan) -> Vec<String> {
+    plan.dirs.iter().map(|id| fs.path(*id)).collect()
+}
+
+#[test]
+fn plan_matches_part2_on_example() -> Result<()> {
+    let fs = crate::filesystem::example();
+    let plan = plan(&fs, Disk::default())?;
+    assert_eq!(plan.need, 8381165);
+    assert_eq!(paths(&fs, &plan), vec!["/d"]);
+    assert_eq!(plan.freed, 24933642);
+    Ok(())
+}
+
+#[test]
+fn plan_combines_directories() -> Result<()> {
+    let fs: FileSystem = "$ cd /
+$ ls
+dir a
+dir b
+dir c
+1 x
+$ cd a
+$ ls
+dir d
+40 y
+$ cd d
+$ ls
+30 z
+$ cd /
+$ cd b
+$ ls
+35 y
+$ cd /
+$ cd c
+$ ls
+110 y"
+        .parse()?;
+    let disk = Disk {
+        capacity: 300,
+        target_free: 186,
+    };
+
+    // 216 used and 84 free, so 102 needed: /c alone would free 110
+    let plan = plan(&fs, disk)?;
+    assert_eq!(plan.need, 102);
+    assert_eq!(paths(&fs, &plan), vec!["/a", "/b"]);
+    assert_eq!(plan.freed, 105);
+    Ok(())
+}
+
+#[test]
+fn plan_matches_brute_force() -> Result<()> {
+    let fs: FileSystem = "$ cd /
+$ ls
+dir a
+dir b
+dir c
+$ cd a
+$ ls
+dir d
+dir e
+7 y
+$ cd d
+$ ls
+11 z
+$ cd ..
+$ cd e
+$ ls
+13 z
+$ cd /
+$ cd b
+$ ls
+dir f
+17 y
+$ cd f
+$ ls
+19 z
+$ cd /
+$ cd c
+$ ls
+23 y"
+        .parse()?;
+    let sizes = fs.sizes();
+    let ids: Vec<DirId> = (1..sizes.len()).collect();
+    let is_inside = |mut id: DirId, outer: DirId| {
+        while let Some(parent) = fs.dir(id).parent {
+            if parent == outer {
+                return true;
+            }
+            id = parent;
+        }
+        false
+    };
+
+    for need in 1..=90 {
+        let expected = (0..1 << ids.len())
+            .map(|mask: usize| {
+                let chosen: Vec<DirId> = ids
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| mask & (1 << bit) != 0)
+                    .map(|(_, id)| *id)
+                    .collect();
+                let nested = chosen
+                    .iter()
+                    .any(|a| chosen.iter().any(|b| is_inside(*a, *b)));
+                let freed: usize = chosen.iter().map(|id| sizes[*id].total).sum();
+                (nested, freed)
+            })
+            .filter(|(nested, freed)| !nested && *freed >= need)
+            .map(|(_, freed)| freed)
+            .min()
+            .unwrap_or(sizes[ROOT].total);
+
+        let disk = Disk {
+            capacity: sizes[ROOT].total,
+            target_free: need,
+        };
+        let plan = plan(&fs, disk)?;
+        assert_eq!(plan.freed, expected, "need {need}");
+        let freed: usize = plan.dirs.iter().map(|id| sizes[*id].total).sum();
+        assert_eq!(freed, plan.freed);
+        assert!(!plan
+            .dirs
+            .iter()
+            .any(|a| plan.dirs.iter().any(|b| is_inside(*a, *b))));
+    }
+    Ok(())
+}
+
+#[test]
+fn plan_rejects_impossible_targets() {
+    let fs = crate::filesystem::example();
+    let disk = Disk {
+        capacity: 70_000_000,
+        target_free: 80_000_000,
+    };
+    assert!(plan(&fs, disk).is_err());
+
+    let disk = Disk {
+        capacity: 1,
+        target_free: 0,
+    };
+    assert!(plan(&fs, disk).is_err());
+}
+
+#[test]
+fn plan_is_empty_when_already_free() -> Result<()> {
+    let fs = crate::filesystem::example();
+    let disk = Disk {
+        capacity: 100_000_000,
+        target_free: 10,
+    };
+    assert_eq!(plan(&fs, disk)?.dirs, vec![]);
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use anyhow::{anyhow, Context, Result};
+
+use crate::filesystem::{DirId, DirSize, FileSystem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry<'a> {
+    Dir(DirId),
+    File {
+        parent: DirId,
+        name: &'a str,
+        size: usize,
+    },
+}
+
+/// Depth first walk below `root`, yielding each entry with its depth (`root` itself is 0).
+/// Directories and files are interleaved in name order.
+pub fn walk(fs: &FileSystem, root: DirId) -> Vec<(usize, Entry<'_>)> {
+    let mut result = vec![];
+    let mut stack = vec![(0, Entry::Dir(root))];
+
+    while let Some((depth, entry)) = stack.pop() {
+        result.push((depth, entry));
+        let Entry::Dir(id) = entry else { continue };
+
+        let dir = fs.dir(id);
+        let mut children: Vec<(&str, Entry)> = dir
+            .dirs
+            .iter()
+            .map(|(name, id)| (name.as_str(), Entry::Dir(*id)))
+            .chain(dir.files.iter().map(|(name, size)| {
+                let file = Entry::File {
+                    parent: id,
+                    name,
+                    size: *size,
+                };
+                (name.as_str(), file)
+            }))
+            .collect();
+        children.sort_by_key(|(name, _)| *name);
+        stack.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|(_, entry)| (depth + 1, entry)),
+        );
+    }
+
+    result
+}
+
+fn entry_path(fs: &FileSystem, entry: Entry) -> String {
+    match entry {
+        Entry::Dir(id) => fs.path(id),
+        Entry::File { parent, name, .. } => match fs.path(parent).as_str() {
+            "/" => format!("/{name}"),
+            path => format!("{path}/{name}"),
+        },
+    }
+}
+
+/// Indented listing in the same style as the puzzle description, with directory sizes.
+pub fn tree(fs: &FileSystem, root: DirId) -> String {
+    let sizes = fs.sizes();
+    walk(fs, root)
+        .into_iter()
+        .map(|(depth, entry)| {
+            let indent = "  ".repeat(depth);
+            match entry {
+                Entry::Dir(id) => {
+                    let size = sizes[id].total;
+                    format!("{indent}- {} (dir, size={size})\n", fs.dir(id).name)
+                }
+                Entry::File { name, size, .. } => {
+                    format!("{indent}- {name} (file, size={size})\n")
+                }
+            }
+        })
+        .collect()
+}
+
+/// Every directory below `root` with its total size, biggest first.
+pub fn du(fs: &FileSystem, root: DirId) -> Vec<(usize, String)> {
+    let sizes = fs.sizes();
+    let mut result: Vec<(usize, String)> = walk(fs, root)
+        .into_iter()
+        .filter_map(|(_, entry)| match entry {
+            Entry::Dir(id) => Some((sizes[id].total, fs.path(id))),
+            Entry::File { .. } => None,
+        })
+        .collect();
+    result.sort_by(|(size1, path1), (size2, path2)| size2.cmp(size1).then(path1.cmp(path2)));
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Dir,
+    File,
+}
+
+/// Filters for [`find`], mirroring the flags of the unix tool.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    pub name: Option<String>,
+    pub kind: Option<Kind>,
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+}
+
+impl Query {
+    /// Parses `-name GLOB`, `-type f|d`, `-size MIN..MAX` (either end optional), `-mindepth N`
+    /// and `-maxdepth N`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut query = Query::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            let value = args.next().context(format!("{flag} needs a value"))?;
+            match flag.as_str() {
+                "-name" => query.name = Some(value.clone()),
+                "-type" => {
+                    query.kind = Some(match value.as_str() {
+                        "d" => Kind::Dir,
+                        "f" => Kind::File,
+                        _ => return Err(anyhow!("unknown type {value}")),
+                    })
+                }
+                "-size" => {
+                    let (min, max) = value.split_once("..").context("size should be MIN..MAX")?;
+                    query.min_size = (!min.is_empty()).then(|| min.parse()).transpose()?;
+                    query.max_size = (!max.is_empty()).then(|| max.parse()).transpose()?;
+                }
+                "-mindepth" => query.min_depth = Some(value.parse()?),
+                "-maxdepth" => query.max_depth = Some(value.parse()?),
+                _ => return Err(anyhow!("unknown flag {flag}")),
+            }
+        }
+
+        Ok(query)
+    }
+
+    fn matches(&self, name: &str, kind: Kind, size: usize, depth: usize) -> bool {
+        self.name.as_ref().is_none_or(|glob| glob_match(glob, name))
+            && self.kind.is_none_or(|wanted| wanted == kind)
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+            && self.min_depth.is_none_or(|min| depth >= min)
+            && self.max_depth.is_none_or(|max| depth <= max)
+    }
+}
+
+/// Paths below `root` matching the query. Directories are matched on their total size.
+pub fn find(fs: &FileSystem, root: DirId, query: &Query) -> Vec<String> {
+    let sizes: Vec<DirSize> = fs.sizes();
+    walk(fs, root)
+        .into_iter()
+        .filter(|(depth, entry)| {
+            let (name, kind, size) = match *entry {
+                Entry::Dir(id) => (fs.dir(id).name.as_str(), Kind::Dir, sizes[id].total),
+                Entry::File { name, size, .. } => (name, Kind::File, size),
+            };
+            query.matches(name, kind, size, *depth)
+        })
+        .map(|(_, entry)| entry_path(fs, entry))
+        .collect()
+}
+
+/// Shell style glob supporting `*` and `?`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // where to resume if the last `*` has to swallow one more character
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    p = star + 1;
+                    n = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[test]
+fn tree_matches_puzzle_layout() {
+    let fs = crate::filesystem::example();
+    assert_eq!(
+        tree(&fs, fs.lookup("/a").unwrap()),
+        "- a (dir, size=94853)
+  - e (dir, size=584)
+    - i (file, size=584)
+  - f (file, size=29116)
+  - g (file, size=2557)
+  - h.lst (file, size=62596)
+"
+    );
+}
+
+#[test]
+fn du_sorts_by_size() {
+    let fs = crate::filesystem::example();
+    assert_eq!(
+        du(&fs, crate::filesystem::ROOT),
+        vec![
+            (48381165, "/".to_string()),
+            (24933642, "/d".to_string()),
+            (94853, "/a".to_string()),
+            (584, "/a/e".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn find_filters() -> Result<()> {
+    let fs = crate::filesystem::example();
+    let root = crate::filesystem::ROOT;
+    let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+
+    let query = Query::parse(&args("-name *.???"))?;
+    assert_eq!(
+        find(&fs, root, &query),
+        vec!["/a/h.lst", "/b.txt", "/c.dat", "/d/d.ext", "/d/d.log"]
+    );
+
+    let query = Query::parse(&args("-type d -size ..100000"))?;
+    assert_eq!(find(&fs, root, &query), vec!["/a", "/a/e"]);
+
+    let query = Query::parse(&args("-type f -size 5000000.. -maxdepth 1"))?;
+    assert_eq!(find(&fs, root, &query), vec!["/b.txt", "/c.dat"]);
+
+    let query = Query::parse(&args("-mindepth 3"))?;
+    assert_eq!(find(&fs, root, &query), vec!["/a/e/i"]);
+    Ok(())
+}
+
+#[test]
+fn glob_match_works() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("d.*", "d.log"));
+    assert!(glob_match("*.l?g", "d.log"));
+    assert!(glob_match("a*b*c", "aXbYbZc"));
+    assert!(!glob_match("a*b*c", "aXbYbZ"));
+    assert!(!glob_match("?", ""));
+}
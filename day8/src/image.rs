@@ -0,0 +1,168 @@
+use crate::{Height, Map, Views};
+
+pub type Rgb = [u8; 3];
+
+const WHITE: Rgb = [255, 255, 255];
+
+/// Netpbm image with one pixel per tree until [`Image::scaled`] blows it up.
+pub struct Image<P> {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<P>,
+}
+
+impl<P: Copy> Image<P> {
+    fn from_grid<T>(grid: &[Vec<T>], mut pixel: impl FnMut(&T) -> P) -> Self {
+        Self {
+            width: grid.first().map_or(0, |row| row.len()),
+            height: grid.len(),
+            pixels: grid.iter().flatten().map(&mut pixel).collect(),
+        }
+    }
+
+    /// Draws every pixel as a `factor` by `factor` block.
+    pub fn scaled(&self, factor: usize) -> Self {
+        let width = self.width * factor;
+        let height = self.height * factor;
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixels[y / factor * self.width + x / factor])
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Image<u8> {
+    /// Binary greyscale PGM.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        out.extend(&self.pixels);
+        out
+    }
+}
+
+impl Image<Rgb> {
+    /// Binary colour PPM.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        out.extend(self.pixels.iter().flatten());
+        out
+    }
+}
+
+fn grey(value: Height, max: Height) -> u8 {
+    (value as u64 * 255 / max.max(1) as u64) as u8
+}
+
+/// Tree heights from black (shortest possible) to white (tallest in the grid).
+pub fn heights(map: &Map) -> Image<u8> {
+    let max = map.inner.iter().flatten().copied().max().unwrap_or(0);
+    Image::from_grid(&map.inner, |height| grey(*height, max))
+}
+
+/// Visible trees in green and hidden ones in grey, both brighter the taller the tree.
+pub fn visibility(map: &Map, views: &Views) -> Image<Rgb> {
+    let max = map.inner.iter().flatten().copied().max().unwrap_or(0);
+    let mut image = Image::from_grid(&map.inner, |height| {
+        let level = grey(*height, max) / 2;
+        [level, level, level]
+    });
+
+    for (pixel, seen) in image.pixels.iter_mut().zip(views.visible.iter().flatten()) {
+        if *seen {
+            *pixel = [0, 127 + pixel[1], 0];
+        }
+    }
+    image
+}
+
+/// Blue through green and yellow to red as `t` goes from 0 to 1.
+fn heat(t: f64) -> Rgb {
+    const STOPS: [Rgb; 5] = [
+        [0, 0, 255],
+        [0, 255, 255],
+        [0, 255, 0],
+        [255, 255, 0],
+        [255, 0, 0],
+    ];
+
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let idx = (scaled as usize).min(STOPS.len() - 2);
+    let frac = scaled - idx as f64;
+    let (from, to) = (STOPS[idx], STOPS[idx + 1]);
+    [0, 1, 2].map(|c| (from[c] as f64 + (to[c] as f64 - from[c] as f64) * frac).round() as u8)
+}
+
+/// Scenic scores on a log scale, since a few trees score far above the rest, with the best
+/// tree in white.
+pub fn scenic(views: &Views) -> Image<Rgb> {
+    let max = views.scenic.iter().flatten().copied().max().unwrap_or(0);
+    let scale = (max as f64).ln_1p().max(f64::MIN_POSITIVE);
+    let mut image = Image::from_grid(&views.scenic, |score| heat((*score as f64).ln_1p() / scale));
+
+    if let Some(best) = views
+        .scenic
+        .iter()
+        .flatten()
+        .position(|score| *score == max)
+    {
+        image.pixels[best] = WHITE;
+    }
+    image
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "30373
+25512
+65332
+33549
+35390";
+
+#[test]
+fn heights_encode_as_pgm() -> anyhow::Result<()> {
+    let map: Map = EXAMPLE.parse()?;
+    let pgm = heights(&map).encode();
+    let header = b"P5\n5 5\n255\n";
+    assert_eq!(&pgm[..header.len()], header);
+    // 3 0 3 7 3 against a tallest tree of 9
+    assert_eq!(&pgm[header.len()..header.len() + 5], &[85, 0, 85, 198, 85]);
+    assert_eq!(pgm.len(), header.len() + 25);
+    Ok(())
+}
+
+#[test]
+fn visibility_highlights_visible_trees() -> anyhow::Result<()> {
+    let map: Map = EXAMPLE.parse()?;
+    let image = visibility(&map, &map.views()?);
+    // the middle 3 is hidden, the 5 above it is visible
+    assert_eq!(image.pixels[12], [42, 42, 42]);
+    assert_eq!(image.pixels[7], [0, 197, 0]);
+    Ok(())
+}
+
+#[test]
+fn scenic_marks_best_tree() -> anyhow::Result<()> {
+    let map: Map = EXAMPLE.parse()?;
+    let image = scenic(&map.views()?);
+    assert_eq!(image.pixels[3 * 5 + 2], WHITE);
+    // edge trees score 0
+    assert_eq!(image.pixels[0], [0, 0, 255]);
+    assert_eq!(image.encode().len(), b"P6\n5 5\n255\n".len() + 25 * 3);
+    Ok(())
+}
+
+#[test]
+fn scaled_repeats_pixels() {
+    let image = Image {
+        width: 2,
+        height: 1,
+        pixels: vec![1u8, 2],
+    }
+    .scaled(2);
+    assert_eq!((image.width, image.height), (4, 2));
+    assert_eq!(image.pixels, vec![1, 1, 2, 2, 1, 1, 2, 2]);
+}
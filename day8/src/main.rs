@@ -1,143 +1,284 @@
-use std::{collections::HashSet, str::FromStr};
+use std::str::FromStr;
 
-use anyhow::{Context, Error, Result};
+use anyhow::{anyhow, Context, Error, Result};
+
+mod image;
+
+type Height = u32;
 
 struct Map {
-    inner: Vec<Vec<u8>>,
+    inner: Vec<Vec<Height>>,
 }
 
 impl FromStr for Map {
     type Err = Error;
 
+    /// Reads one digit per tree, as in the puzzle.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let inner: Result<Vec<Vec<u8>>> = s
+        let rows = s
             .lines()
-            .map(|line| {
-                line.chars()
-                    .map(|c| Ok(c.to_digit(10).context("couldn't convert to digit")? as u8))
+            .enumerate()
+            .map(|(row, line)| {
+                line.trim_end()
+                    .chars()
+                    .enumerate()
+                    .map(|(column, c)| {
+                        c.to_digit(10).context(format!(
+                            "row {}, column {}: couldn't convert {c:?} to digit",
+                            row + 1,
+                            column + 1
+                        ))
+                    })
                     .collect::<Result<Vec<_>>>()
             })
-            .collect();
-
-        Ok(Self { inner: inner? })
+            .collect::<Result<Vec<_>>>()?;
+        Map::from_rows(rows)
     }
 }
 
-fn visible_in_row<'a>(
-    (direction, position): (char, usize),
-    mut items: impl Iterator<Item = (usize, &'a u8)>,
-) -> Vec<(usize, usize)> {
-    let mut result = vec![];
-    let (idx, mut tallest_seen) = items.next().expect("should have an item");
-
-    fn into_coord(direction: char, idx: usize, position: usize) -> (usize, usize) {
-        match direction {
-            'x' => (position, idx),
-            'y' => (idx, position),
-            _ => panic!("unhandled direction"),
-        }
+impl Map {
+    /// Reads heights of any number of digits, separated by commas or whitespace. A grid one
+    /// tree wide reads just as well as digits, which is why the format isn't guessed.
+    fn parse_separated(s: &str) -> Result<Self> {
+        let is_separator = |c: char| c == ',' || c.is_whitespace();
+        let rows = s
+            .lines()
+            .enumerate()
+            .map(|(row, line)| {
+                line.split(is_separator)
+                    .filter(|height| !height.is_empty())
+                    .enumerate()
+                    .map(|(column, height)| {
+                        height.parse().context(format!(
+                            "row {}, column {}: couldn't parse height {height:?}",
+                            row + 1,
+                            column + 1
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Map::from_rows(rows)
     }
 
-    result.push(into_coord(direction, idx, position));
-
-    for (idx, item) in items {
-        if tallest_seen < item {
-            result.push(into_coord(direction, idx, position));
-            tallest_seen = item
+    fn from_rows(inner: Vec<Vec<Height>>) -> Result<Self> {
+        let width = inner.first().map(|first| first.len()).context("no trees")?;
+        if let Some((row, trees)) = inner
+            .iter()
+            .enumerate()
+            .find(|(_, trees)| trees.len() != width)
+        {
+            return Err(anyhow!(
+                "row {} has {} trees but row 1 has {width}",
+                row + 1,
+                trees.len()
+            ));
         }
+
+        Ok(Self { inner })
     }
+}
 
-    result
+type Grid<T> = Vec<Vec<T>>;
+
+/// What every tree can see, looking out in each direction of a [`Sight`].
+struct Views {
+    /// Whether the tree can be seen from outside the grid.
+    visible: Grid<bool>,
+    /// Product of the viewing distances in each direction.
+    scenic: Grid<usize>,
 }
 
-impl Map {
-    fn visible_trees(&self) -> Result<usize> {
-        let y_len = self.inner.len();
-        let x_len = self
-            .inner
-            .first()
-            .map(|first| first.len())
-            .context("couldn't get x len")?;
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Directions {
+    /// Along the rows and columns, as in the puzzle.
+    #[default]
+    Axes,
+    /// The axes and the diagonals.
+    All,
+}
 
-        let mut seen = HashSet::new();
+impl Directions {
+    fn steps(self) -> &'static [(isize, isize)] {
+        const AXES: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+        const ALL: [(isize, isize); 8] = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
 
-        for y in 0..y_len {
-            let row = &self.inner[y];
-            //left forwards
-            let forwards = visible_in_row(('y', y), row.iter().enumerate());
-            seen.extend(forwards);
-            // right backwards
-            let backwards = visible_in_row(('y', y), row.iter().enumerate().rev());
-            seen.extend(backwards);
+        match self {
+            Directions::Axes => &AXES,
+            Directions::All => &ALL,
         }
+    }
+}
 
-        for x in 0..x_len {
-            let row = &self.inner.iter().map(|row| &row[x]).enumerate();
-            //left forwards
-            let forwards = visible_in_row(('x', x), row.clone());
-            seen.extend(forwards);
-            //right backwards
-            let backwards = visible_in_row(('x', x), row.clone().rev());
-            seen.extend(backwards);
+/// How far and which ways trees can see.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Sight {
+    directions: Directions,
+    /// Trees past this distance can't be seen, and only trees this close to the edge can be
+    /// seen from outside the grid.
+    max_distance: Option<usize>,
+}
+
+impl Sight {
+    /// Parses `--diagonal` and `--max-distance N`.
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut sight = Sight::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--diagonal" => sight.directions = Directions::All,
+                "--max-distance" => {
+                    let value = args.next().context("--max-distance needs a value")?;
+                    sight.max_distance = Some(value.parse()?);
+                }
+                _ => return Err(anyhow!("unknown flag {flag}")),
+            }
         }
 
-        Ok(seen.len())
+        Ok(sight)
     }
+}
 
-    fn most_scenic(&self) -> Result<usize> {
+impl Map {
+    fn dimensions(&self) -> Result<(usize, usize)> {
         let y_len = self.inner.len();
         let x_len = self
             .inner
             .first()
             .map(|first| first.len())
             .context("couldn't get x len")?;
+        Ok((x_len, y_len))
+    }
 
-        let mut max = 0;
+    fn views(&self) -> Result<Views> {
+        self.views_with(Sight::default())
+    }
+
+    fn views_with(&self, sight: Sight) -> Result<Views> {
+        let (x_len, y_len) = self.dimensions()?;
+        let mut views = Views {
+            visible: vec![vec![false; x_len]; y_len],
+            scenic: vec![vec![1; x_len]; y_len],
+        };
+        let step = |(x, y): (usize, usize), (dx, dy): (isize, isize)| {
+            let x = x.checked_add_signed(dx).filter(|x| *x < x_len)?;
+            let y = y.checked_add_signed(dy).filter(|y| *y < y_len)?;
+            Some((x, y))
+        };
 
-        for x in 0..x_len {
+        // walking a line in one direction works out what every tree on it sees looking back
+        // the other way, so start a walk from every tree with nothing behind it
+        for &(dx, dy) in sight.directions.steps() {
             for y in 0..y_len {
-                let result = calculate_score((x, y), (x_len, y_len), &self.inner);
-                if result > max {
-                    max = result;
+                for x in 0..x_len {
+                    if step((x, y), (-dx, -dy)).is_some() {
+                        continue;
+                    }
+                    let line = std::iter::successors(Some((x, y)), |pos| step(*pos, (dx, dy)));
+                    self.look_along(line, sight.max_distance, &mut views);
                 }
             }
         }
 
-        Ok(max)
+        Ok(views)
     }
-}
 
-fn calculate_score((x, y): (usize, usize), (_, y_len): (usize, usize), map: &[Vec<u8>]) -> usize {
-    let tree_height = map[y][x];
-    [
-        visible_from_tree(tree_height, map[y][x..].iter().skip(1)), // right
-        visible_from_tree(tree_height, map[y][..x].iter().rev()), //left
-        visible_from_tree(
-            tree_height,
-            map.iter().rev().skip(y_len - y).map(|line| &line[x]),
-        ), // up
-        visible_from_tree(tree_height, map.iter().skip(y + 1).map(|line| &line[x])), // down
-    ]
-    .iter()
-    .product()
-}
+    /// Walks a line of trees keeping a stack of the trees not yet hidden behind a taller one,
+    /// so each tree finds the nearest tree at least as tall back along the line in amortised
+    /// constant time.
+    fn look_along(
+        &self,
+        line: impl Iterator<Item = (usize, usize)>,
+        max_distance: Option<usize>,
+        views: &mut Views,
+    ) {
+        let max_distance = max_distance.unwrap_or(usize::MAX);
+        let mut stack: Vec<(usize, Height)> = vec![];
 
-fn visible_from_tree<'a>(tree_height: u8, items: impl Iterator<Item = &'a u8>) -> usize {
-    let mut result = 0;
+        for (idx, (x, y)) in line.enumerate() {
+            let height = self.inner[y][x];
+            while stack.last().is_some_and(|(_, taller)| *taller < height) {
+                stack.pop();
+            }
 
-    for item in items {
-        result += 1;
-        if tree_height <= *item {
-            break;
+            match stack.last() {
+                Some((blocker, _)) => views.scenic[y][x] *= (idx - blocker).min(max_distance),
+                None => {
+                    // sees all the way to the edge
+                    views.scenic[y][x] *= idx.min(max_distance);
+                    views.visible[y][x] |= idx <= max_distance;
+                }
+            }
+            stack.push((idx, height));
         }
     }
 
-    result
+    fn visible_trees(&self) -> Result<usize> {
+        Ok(self
+            .views()?
+            .visible
+            .iter()
+            .flatten()
+            .filter(|seen| **seen)
+            .count())
+    }
+
+    fn most_scenic(&self) -> Result<usize> {
+        self.views()?
+            .scenic
+            .into_iter()
+            .flatten()
+            .max()
+            .context("couldn't get any trees")
+    }
 }
 
 fn main() -> Result<()> {
-    let input = include_str!("../input.txt");
-    let map: Map = input.parse()?;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // `--separated FILE` reads separated heights from FILE instead of the puzzle input
+    let map = match args.first().map(String::as_str) {
+        Some("--separated") => {
+            let path = args.get(1).context("--separated needs a file")?;
+            let map = Map::parse_separated(&std::fs::read_to_string(path)?)?;
+            args.drain(..2);
+            map
+        }
+        _ => include_str!("../input.txt").parse()?,
+    };
+
+    if let Some("export") = args.first().map(String::as_str) {
+        let dir = args.get(1).context("export needs a directory")?;
+        let scale = args
+            .get(2)
+            .map(|scale| scale.parse())
+            .transpose()?
+            .unwrap_or(1);
+        return export(&map, dir.as_ref(), scale);
+    }
+    if let Some("sight") = args.first().map(String::as_str) {
+        let views = map.views_with(Sight::parse(&args[1..])?)?;
+        let visible = views.visible.iter().flatten().filter(|seen| **seen).count();
+        let best = views
+            .scenic
+            .iter()
+            .flatten()
+            .max()
+            .context("couldn't get any trees")?;
+        println!("visible: {visible}");
+        println!("most scenic: {best}");
+        return Ok(());
+    }
+
     let part1 = map.visible_trees()?;
     println!("part1: {part1}");
     let part2 = map.most_scenic()?;
@@ -145,6 +286,28 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Writes `heights.pgm`, `visible.ppm` and `scenic.ppm` into `dir`.
+fn export(map: &Map, dir: &std::path::Path, scale: usize) -> Result<()> {
+    if scale == 0 {
+        return Err(anyhow!("scale has to be at least 1"));
+    }
+    let views = map.views()?;
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join("heights.pgm"),
+        image::heights(map).scaled(scale).encode(),
+    )?;
+    std::fs::write(
+        dir.join("visible.ppm"),
+        image::visibility(map, &views).scaled(scale).encode(),
+    )?;
+    std::fs::write(
+        dir.join("scenic.ppm"),
+        image::scenic(&views).scaled(scale).encode(),
+    )?;
+    Ok(())
+}
+
 #[test]
 fn part1() -> Result<()> {
     let input = r#"30373
@@ -166,8 +329,9 @@ fn calculate_score_works() -> Result<()> {
 33549
 35390"#;
     let map: Map = input.parse()?;
-    assert_eq!(calculate_score((2, 1), (5, 5), &map.inner), 4);
-    assert_eq!(calculate_score((2, 3), (5, 5), &map.inner), 8);
+    let scenic = map.views()?.scenic;
+    assert_eq!(scenic[1][2], 4);
+    assert_eq!(scenic[3][2], 8);
     Ok(())
 }
 
@@ -182,3 +346,122 @@ fn part2_works() -> Result<()> {
     assert_eq!(map.most_scenic().unwrap(), 8);
     Ok(())
 }
+
+#[test]
+fn visibility_grid_works() -> Result<()> {
+    let input = r#"30373
+25512
+65332
+33549
+35390"#;
+    let map: Map = input.parse()?;
+    let visible = map.views()?.visible;
+    assert_eq!(visible[1], vec![true, true, true, false, true]);
+    assert_eq!(visible[2], vec![true, true, false, true, true]);
+    assert_eq!(visible[3], vec![true, false, true, false, true]);
+    Ok(())
+}
+
+#[cfg(test)]
+fn look_from_tree(
+    map: &Map,
+    (x, y): (usize, usize),
+    step: (isize, isize),
+    sight: Sight,
+) -> (usize, bool) {
+    let height = map.inner[y][x];
+    let max_distance = sight.max_distance.unwrap_or(usize::MAX);
+    let mut distance = 0;
+    let (mut cx, mut cy) = (x as isize, y as isize);
+    loop {
+        cx += step.0;
+        cy += step.1;
+        let Some(tree) = map
+            .inner
+            .get(cy as usize)
+            .and_then(|row| row.get(cx as usize))
+        else {
+            return (distance.min(max_distance), distance <= max_distance);
+        };
+        distance += 1;
+        if *tree >= height {
+            return (distance.min(max_distance), false);
+        }
+    }
+}
+
+#[test]
+fn views_with_matches_looking_from_each_tree() -> Result<()> {
+    let input = r#"30373
+25512
+65332
+33549
+35390"#;
+    let map: Map = input.parse()?;
+
+    for directions in [Directions::Axes, Directions::All] {
+        for max_distance in [None, Some(0), Some(1), Some(2), Some(3)] {
+            let sight = Sight {
+                directions,
+                max_distance,
+            };
+            let views = map.views_with(sight)?;
+            for y in 0..5 {
+                for x in 0..5 {
+                    let looks: Vec<(usize, bool)> = directions
+                        .steps()
+                        .iter()
+                        .map(|step| look_from_tree(&map, (x, y), *step, sight))
+                        .collect();
+                    let scenic: usize = looks.iter().map(|(distance, _)| distance).product();
+                    let visible = looks.iter().any(|(_, seen)| *seen);
+                    assert_eq!(views.scenic[y][x], scenic, "{sight:?} ({x}, {y})");
+                    assert_eq!(views.visible[y][x], visible, "{sight:?} ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    let diagonal = Sight {
+        directions: Directions::All,
+        max_distance: None,
+    };
+    assert_eq!(map.views_with(diagonal)?.scenic[3][2], 16);
+    Ok(())
+}
+
+#[test]
+fn parse_rejects_jagged_rows() {
+    let err = "30373\n2551\n65332".parse::<Map>().err().unwrap();
+    assert_eq!(err.to_string(), "row 2 has 4 trees but row 1 has 5");
+
+    let err = "303\n2x5".parse::<Map>().err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "row 2, column 2: couldn't convert 'x' to digit"
+    );
+}
+
+#[test]
+fn parse_separated_heights() -> Result<()> {
+    let map = Map::parse_separated("10, 2,30\n4 15   6\n 7,8,  90")?;
+    assert_eq!(
+        map.inner,
+        vec![vec![10, 2, 30], vec![4, 15, 6], vec![7, 8, 90]]
+    );
+    assert_eq!(map.visible_trees()?, 9);
+
+    let err = Map::parse_separated("10 2\n4 x").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "row 2, column 2: couldn't parse height \"x\""
+    );
+
+    // one column of tall trees, or two columns of digits, depending on who's asking
+    let map = Map::parse_separated("10\n20\n30")?;
+    assert_eq!(map.inner, vec![vec![10], vec![20], vec![30]]);
+    let map: Map = "10\n20\n30".parse()?;
+    assert_eq!(map.inner, vec![vec![1, 0], vec![2, 0], vec![3, 0]]);
+    assert!("10 2\n4 5".parse::<Map>().is_err());
+    Ok(())
+}
@@ -1,28 +1,38 @@
-#![feature(array_windows)]
 use anyhow::{anyhow, Context, Error, Result};
-use std::{
-    collections::{HashMap, HashSet},
-    str::FromStr,
-};
+use std::{collections::HashSet, str::FromStr};
 
-enum Dir {
-    Left,
-    Down,
-    Right,
-    Up,
-}
+mod render;
+use render::{animate, Every};
+
+/// A step of one in any combination of the axes, e.g. `U`, `UL` or `DRF`.
+#[derive(Debug, PartialEq, Eq)]
+struct Dir(Coord);
 
 impl FromStr for Dir {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "L" => Dir::Left,
-            "R" => Dir::Right,
-            "U" => Dir::Up,
-            "D" => Dir::Down,
-            _ => return Err(anyhow!("couldn't parse direction")),
-        })
+        let mut delta = [0; 3];
+        for c in s.chars() {
+            let (axis, step) = match c {
+                'L' => (0, -1),
+                'R' => (0, 1),
+                'U' => (1, -1),
+                'D' => (1, 1),
+                'B' => (2, -1),
+                'F' => (2, 1),
+                _ => return Err(anyhow!("couldn't parse direction {s}")),
+            };
+            if delta[axis] != 0 {
+                return Err(anyhow!("direction {s} moves along an axis twice"));
+            }
+            delta[axis] = step;
+        }
+
+        if delta == [0; 3] {
+            return Err(anyhow!("couldn't parse direction {s}"));
+        }
+        Ok(Dir(delta))
     }
 }
 
@@ -46,23 +56,41 @@ fn parse(s: &str) -> Result<Vec<Move>> {
     s.lines().map(|line| line.parse()).collect()
 }
 
-type Coord = (isize, isize);
+/// `x` grows to the right, `y` downwards and `z` forwards. Ropes moving in a plane stay at
+/// `z` 0.
+type Coord = [isize; 3];
 
-struct Map<const N: usize> {
-    knots: [Coord; N],
-    seen: HashSet<Coord>,
+/// A rope of any number of knots, each following the one in front of it.
+struct Map {
+    knots: Vec<Coord>,
+    /// How far a knot can get from the one in front before it has to move.
+    slack: usize,
+    /// Every position each knot has been in, head first.
+    seen: Vec<HashSet<Coord>>,
 }
 
-impl<const N: usize> Default for Map<N> {
-    fn default() -> Self {
-        Self {
-            knots: [(0, 0); N],
-            seen: Default::default(),
+impl Map {
+    fn new(knots: usize) -> Result<Self> {
+        if knots == 0 {
+            return Err(anyhow!("a rope needs at least one knot"));
         }
+
+        Ok(Self {
+            knots: vec![[0; 3]; knots],
+            slack: 1,
+            seen: vec![HashSet::from([[0; 3]]); knots],
+        })
+    }
+
+    fn with_slack(mut self, slack: usize) -> Self {
+        self.slack = slack;
+        self
+    }
+
+    fn tail_seen(&self) -> &HashSet<Coord> {
+        self.seen.last().expect("rope has at least one knot")
     }
-}
 
-impl<const N: usize> Map<N> {
     fn apply_moves(&mut self, moves: &[Move]) -> Result<()> {
         for m in moves {
             self.apply_move(m)?;
@@ -70,98 +98,67 @@ impl<const N: usize> Map<N> {
         Ok(())
     }
 
-    fn apply_move(&mut self, Move { dir, steps }: &Move) -> Result<()> {
-        let index = core::array::from_fn::<_, N, _>(|i| i);
+    fn apply_move(
+        &mut self,
+        Move {
+            dir: Dir(delta),
+            steps,
+        }: &Move,
+    ) -> Result<()> {
         for _ in 0..*steps {
-            let mut head = self.knots.get_mut(0).context("couldn't get head")?;
-
-            match dir {
-                Dir::Left => head.0 -= 1,
-                Dir::Down => head.1 += 1,
-                Dir::Right => head.0 += 1,
-                Dir::Up => head.1 -= 1,
-            }
-
-            for [head, tail] in index.array_windows() {
-                let head = &self.knots[*head];
-                let old_tail = &self.knots[*tail];
-                let new_tail = calculate_tail_pos(old_tail, head);
-                self.knots[*tail] = new_tail;
-            }
-
-            let tail = self.knots.last().context("couldn't get tail")?;
-            self.seen.insert(*tail);
+            self.step(*delta);
         }
+        Ok(())
+    }
 
-        // Debug printing
-        //
-        // let map = self
-        //     .knots
-        //     .iter()
-        //     .cloned()
-        //     .enumerate()
-        //     .map(|(num, coord)| (coord, num))
-        //     .collect::<HashMap<Coord, usize>>();
-
-        // for y in -25..25 {
-        //     for x in -25..25 {
-        //         if let Some(key) = map.get(&(x, y)) {
-        //             print!("{key}");
-        //         } else {
-        //             print!(".");
-        //         }
-        //     }
-        //     println!();
-        // }
-        // println!();
+    fn step(&mut self, delta: Coord) {
+        for (axis, step) in self.knots[0].iter_mut().zip(delta) {
+            *axis += step;
+        }
+        self.seen[0].insert(self.knots[0]);
 
-        Ok(())
+        for idx in 1..self.knots.len() {
+            self.knots[idx] = follow(self.knots[idx], self.knots[idx - 1], self.slack);
+            self.seen[idx].insert(self.knots[idx]);
+        }
     }
 }
 
-fn calculate_tail_pos((x1, y1): &Coord, (x2, y2): &Coord) -> Coord {
-    let difference = (x1 - x2).abs() + (y1 - y2).abs();
-    if difference > 1 {
-        if x1 == x2 {
-            (*x1, (*y1 + *y2) / 2)
-        } else if y1 == y2 {
-            ((*x1 + *x2) / 2, *y1)
-        } else if difference >= 3 {
-            let x = if (x2 - x1).is_positive() {
-                *x1 + 1
-            } else {
-                *x1 - 1
-            };
-            let y = if (y2 - y1).is_positive() {
-                *y1 + 1
-            } else {
-                *y1 - 1
-            };
-            (x, y)
-        } else {
-            (*x1, *y1)
-        }
-    } else {
-        (*x1, *y1)
+/// Moves `knot` one step towards `leader`, diagonally if need be, once it's further than
+/// `slack` away along any axis.
+fn follow(knot: Coord, leader: Coord, slack: usize) -> Coord {
+    let far = knot
+        .iter()
+        .zip(leader)
+        .any(|(from, to)| (to - from).unsigned_abs() > slack);
+    if !far {
+        return knot;
     }
+    std::array::from_fn(|axis| knot[axis] + (leader[axis] - knot[axis]).signum())
 }
 
 fn part1(input: &str) -> Result<usize> {
     let moves = parse(input)?;
-    let mut map: Map<2> = Default::default();
+    let mut map = Map::new(2)?;
     map.apply_moves(&moves)?;
-    Ok(map.seen.len())
+    Ok(map.tail_seen().len())
 }
 
 fn part2(input: &str) -> Result<usize> {
     let moves = parse(input)?;
-    let mut map: Map<10> = Default::default();
+    let mut map = Map::new(10)?;
     map.apply_moves(&moves)?;
-    Ok(map.seen.len())
+    Ok(map.tail_seen().len())
 }
 
 fn main() -> Result<()> {
     let input = include_str!("../input.txt");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some("rope") = args.first().map(String::as_str) {
+        return rope(input, &args[1..]);
+    }
+
     let part1 = part1(input)?;
     println!("part1: {part1}");
 
@@ -171,6 +168,48 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Simulates `--knots N` (default 10) with `--slack S` (default 1) and prints how many
+/// positions each knot visited. `--render move|step` draws the rope after every move or
+/// step, into numbered files under `--frames DIR` if given.
+fn rope(input: &str, args: &[String]) -> Result<()> {
+    let (mut knots, mut slack, mut every, mut frames) = (10, 1, None, None);
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().context(format!("{flag} needs a value"))?;
+        match flag.as_str() {
+            "--knots" => knots = value.parse()?,
+            "--slack" => slack = value.parse()?,
+            "--render" => every = Some(value.parse()?),
+            "--frames" => frames = Some(std::path::PathBuf::from(value)),
+            _ => return Err(anyhow!("unknown flag {flag}")),
+        }
+    }
+
+    let mut map = Map::new(knots)?.with_slack(slack);
+    let moves = parse(input)?;
+    match (every, frames) {
+        (None, None) => map.apply_moves(&moves)?,
+        (every, Some(dir)) => {
+            std::fs::create_dir_all(&dir)?;
+            let mut count = 0;
+            animate(&mut map, &moves, every.unwrap_or(Every::Move), |frame| {
+                count += 1;
+                std::fs::write(dir.join(format!("frame_{count:05}.txt")), frame)?;
+                Ok(())
+            })?;
+        }
+        (Some(every), None) => animate(&mut map, &moves, every, |frame| {
+            println!("{frame}");
+            Ok(())
+        })?,
+    }
+
+    for (knot, seen) in map.seen.iter().enumerate() {
+        println!("knot {knot}: {}", seen.len());
+    }
+    Ok(())
+}
+
 #[test]
 fn part1_works() {
     let input = r#"R 4
@@ -209,3 +248,55 @@ L 25
 U 20"#;
     assert_eq!(part2(input).unwrap(), 36);
 }
+
+#[test]
+fn follow_works() {
+    assert_eq!(follow([0, 0, 0], [1, 1, 0], 1), [0, 0, 0]);
+    assert_eq!(follow([0, 0, 0], [2, 0, 0], 1), [1, 0, 0]);
+    assert_eq!(follow([0, 0, 0], [2, 1, 0], 1), [1, 1, 0]);
+    assert_eq!(follow([0, 0, 0], [-2, -2, 0], 1), [-1, -1, 0]);
+    assert_eq!(follow([0, 0, 0], [2, 2, 0], 2), [0, 0, 0]);
+    assert_eq!(follow([0, 0, 0], [3, -1, 0], 2), [1, -1, 0]);
+    assert_eq!(follow([0, 0, 0], [1, 1, 2], 1), [1, 1, 1]);
+    assert_eq!(follow([0, 0, 0], [0, 0, -2], 1), [0, 0, -1]);
+}
+
+#[test]
+fn parses_diagonal_and_3d_directions() {
+    assert_eq!("UL".parse::<Dir>().unwrap(), Dir([-1, -1, 0]));
+    assert_eq!("DR".parse::<Dir>().unwrap(), Dir([1, 1, 0]));
+    assert_eq!("F".parse::<Dir>().unwrap(), Dir([0, 0, 1]));
+    assert_eq!("UB".parse::<Dir>().unwrap(), Dir([0, -1, -1]));
+    assert!("UD".parse::<Dir>().is_err());
+    assert!("X".parse::<Dir>().is_err());
+    assert!("".parse::<Dir>().is_err());
+}
+
+#[test]
+fn diagonal_and_3d_moves_drag_the_rope() -> Result<()> {
+    let mut map = Map::new(2)?;
+    map.apply_moves(&parse("UR 3")?)?;
+    assert_eq!(map.knots, vec![[3, -3, 0], [2, -2, 0]]);
+    assert_eq!(map.tail_seen().len(), 3);
+
+    let mut map = Map::new(3)?;
+    map.apply_moves(&parse("F 3\nU 2\nB 1")?)?;
+    assert_eq!(map.knots, vec![[0, -2, 2], [0, -1, 3], [0, -1, 2]]);
+    assert_eq!(map.tail_seen().len(), 3);
+    Ok(())
+}
+
+#[test]
+fn every_knot_is_tracked() -> Result<()> {
+    let mut map = Map::new(3)?;
+    map.apply_moves(&parse("R 4")?)?;
+    let lens: Vec<usize> = map.seen.iter().map(|seen| seen.len()).collect();
+    assert_eq!(lens, vec![5, 4, 3]);
+
+    let mut slack = Map::new(2)?.with_slack(2);
+    slack.apply_moves(&parse("R 4")?)?;
+    assert_eq!(slack.tail_seen().len(), 3);
+
+    assert!(Map::new(0).is_err());
+    Ok(())
+}
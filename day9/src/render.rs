@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::{Dir, Map, Move};
+
+/// How often to draw a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Every {
+    Move,
+    Step,
+}
+
+impl FromStr for Every {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "move" => Every::Move,
+            "step" => Every::Step,
+            _ => return Err(anyhow!("expected move or step, got {s}")),
+        })
+    }
+}
+
+fn label(knot: usize) -> char {
+    match knot {
+        0 => 'H',
+        _ => char::from_digit(knot as u32, 36).unwrap_or('*'),
+    }
+}
+
+/// Draws the rope in the puzzle's style over the smallest box holding every knot and every
+/// cell the tail has been in. Ropes leaving the plane are flattened onto `x` and `y`.
+pub fn render(map: &Map) -> String {
+    let tail_seen = map.tail_seen();
+    let cells = || map.knots.iter().chain(tail_seen);
+    let min_x = cells().map(|knot| knot[0]).min().unwrap_or(0);
+    let max_x = cells().map(|knot| knot[0]).max().unwrap_or(0);
+    let min_y = cells().map(|knot| knot[1]).min().unwrap_or(0);
+    let max_y = cells().map(|knot| knot[1]).max().unwrap_or(0);
+
+    let width = (max_x - min_x + 1) as usize;
+    let height = (max_y - min_y + 1) as usize;
+    let mut grid = vec![vec!['.'; width]; height];
+    let mut draw = |[x, y, _]: [isize; 3], c: char| {
+        grid[(y - min_y) as usize][(x - min_x) as usize] = c;
+    };
+
+    for cell in tail_seen {
+        draw(*cell, '#');
+    }
+    draw([0; 3], 's');
+    // knots nearer the head cover the ones behind
+    for (knot, pos) in map.knots.iter().enumerate().rev() {
+        draw(*pos, label(knot));
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+
+/// Applies the moves, handing `frame` the starting state and then the state after every
+/// move or step.
+pub fn animate(
+    map: &mut Map,
+    moves: &[Move],
+    every: Every,
+    mut frame: impl FnMut(String) -> Result<()>,
+) -> Result<()> {
+    frame(render(map))?;
+
+    for Move {
+        dir: Dir(delta),
+        steps,
+    } in moves
+    {
+        for _ in 0..*steps {
+            map.step(*delta);
+            if every == Every::Step {
+                frame(render(map))?;
+            }
+        }
+        if every == Every::Move {
+            frame(render(map))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn render_fits_the_rope() -> Result<()> {
+    let mut map = Map::new(3)?;
+    map.apply_moves(&crate::parse("R 4\nU 2")?)?;
+    assert_eq!(render(&map), "....H\n...21\ns##..\n");
+    Ok(())
+}
+
+#[test]
+fn animate_draws_every_step_or_move() -> Result<()> {
+    let moves = crate::parse("R 2\nU 1")?;
+
+    let mut frames = vec![];
+    animate(&mut Map::new(2)?, &moves, Every::Move, |frame| {
+        frames.push(frame);
+        Ok(())
+    })?;
+    assert_eq!(frames, ["H\n", "s1H\n", "..H\ns1.\n"]);
+
+    let mut count = 0;
+    animate(&mut Map::new(2)?, &moves, Every::Step, |_| {
+        count += 1;
+        Ok(())
+    })?;
+    assert_eq!(count, 4);
+    Ok(())
+}